@@ -12,6 +12,9 @@ const TARGET_BROWSER = ['es2020'];
 const EXTERNALS_BROWSER = [
     'xterm',
     'xterm-addon-fit',
+    'xterm-addon-search',
+    'xterm-addon-serialize',
+    'xterm-addon-unicode11',
     'xterm-addon-web-links',
     'xterm-addon-webgl',
 ];
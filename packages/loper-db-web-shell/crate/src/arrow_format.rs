@@ -0,0 +1,740 @@
+use arrow::array::{
+    Array, ArrayRef, BinaryArray, FixedSizeBinaryArray, FixedSizeListArray, Float32Array,
+    Float64Array, LargeBinaryArray, LargeListArray, LargeStringArray, ListArray, MapArray,
+    StringArray, StructArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+    TimestampNanosecondArray, TimestampSecondArray, UnionArray,
+};
+use arrow::datatypes::{DataType, TimeUnit};
+use arrow::error::{ArrowError, Result};
+use arrow::util::display::array_value_to_string;
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use std::fmt::Write;
+use unicode_width::UnicodeWidthChar;
+
+/// The default nesting depth of formatted values
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+/// The default number of elements of formatted nested values
+pub const DEFAULT_MAX_LENGTH: usize = 10;
+/// The default number of terminal columns of formatted text values
+pub const DEFAULT_MAX_WIDTH: usize = 80;
+/// The number of bytes shown of truncated binary values
+pub const BINARY_PREVIEW_BYTES: usize = 16;
+
+/// The options for formatting numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// The separator of the digit groups (if any)
+    pub group_separator: Option<char>,
+    /// The decimal separator
+    pub decimal_separator: char,
+    /// The number of fraction digits of floats (if fixed)
+    pub precision: Option<usize>,
+    /// Use the scientific notation for floats with an absolute exponent of at least this (if any)
+    pub scientific_exponent: Option<u32>,
+    /// Align numeric columns on the decimal separator
+    pub align_decimal: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group_separator: None,
+            decimal_separator: '.',
+            precision: None,
+            scientific_exponent: None,
+            align_decimal: false,
+        }
+    }
+}
+
+/// Get the offset of a time zone in seconds east of UTC at a UTC timestamp in milliseconds.
+/// Returns None if the time zone is unknown.
+pub type ZoneOffsetFn = fn(&str, i64) -> Option<i32>;
+
+/// The time zone that timestamps with a time zone are shown in
+#[derive(Clone, Debug)]
+pub struct DisplayTimeZone {
+    /// The IANA name of the time zone, e.g. Europe/Berlin
+    pub name: String,
+    /// Resolves the offsets of the time zone, None for UTC
+    pub offset: Option<ZoneOffsetFn>,
+}
+
+impl DisplayTimeZone {
+    /// The UTC time zone
+    pub fn utc() -> Self {
+        Self {
+            name: "UTC".to_string(),
+            offset: None,
+        }
+    }
+
+    /// Get the offset in seconds east of UTC at a UTC timestamp in milliseconds
+    fn offset_at(&self, ms: i64) -> i32 {
+        self.offset
+            .and_then(|offset| offset(&self.name, ms))
+            .unwrap_or(0)
+    }
+}
+
+/// The options for formatting values
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// The maximum nesting depth of lists, structs and maps
+    pub max_depth: usize,
+    /// The maximum number of list elements, struct fields and map entries
+    pub max_length: usize,
+    /// The maximum terminal columns of text values, None shows text and binary values in full
+    pub max_width: Option<usize>,
+    /// The format of numbers
+    pub numbers: NumberFormat,
+    /// The time zone of timestamps with a time zone
+    pub time_zone: DisplayTimeZone,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_length: DEFAULT_MAX_LENGTH,
+            max_width: Some(DEFAULT_MAX_WIDTH),
+            numbers: NumberFormat::default(),
+            time_zone: DisplayTimeZone::utc(),
+        }
+    }
+}
+
+/// Is a data type a numeric type?
+pub fn is_numeric(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Decimal(_, _)
+    )
+}
+
+/// Is a data type a nested type?
+fn is_nested(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::List(_)
+            | DataType::LargeList(_)
+            | DataType::FixedSizeList(_, _)
+            | DataType::Struct(_)
+            | DataType::Map(_, _)
+            | DataType::Union(_, _)
+    )
+}
+
+/// Is a data type a text type?
+fn is_text(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 => true,
+        DataType::Dictionary(_, value_type) => {
+            matches!(**value_type, DataType::Utf8 | DataType::LargeUtf8)
+        }
+        _ => false,
+    }
+}
+
+/// Downcast an array to the array type of its data type
+fn downcast<T: 'static>(column: &ArrayRef) -> Result<&T> {
+    column.as_any().downcast_ref::<T>().ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!(
+            "could not downcast array of type {}",
+            column.data_type()
+        ))
+    })
+}
+
+/// Format a value of an array.
+/// Nested values are formatted as compact JSON-like text, e.g. [1, 2] or {a: 1, b: "x"}.
+/// Binary values are formatted as hex, e.g. \x0a1b, and control characters in text are escaped.
+pub fn format_value(column: &ArrayRef, row: usize, options: &FormatOptions) -> Result<String> {
+    if column.is_null(row) {
+        return array_value_to_string(column, row);
+    }
+    if let Some(bytes) = binary_value(column, row)? {
+        return Ok(format_binary(bytes, options.max_width.is_some()));
+    }
+    if is_text(column.data_type()) {
+        let text = escape_control_characters(&array_value_to_string(column, row)?);
+        return Ok(truncate_text(text, options.max_width));
+    }
+    // Numbers in nested values keep the plain format, group separators would be ambiguous there
+    if is_numeric(column.data_type()) && options.numbers != NumberFormat::default() {
+        return format_number(column, row, &options.numbers);
+    }
+    if let DataType::Timestamp(unit, Some(_)) = column.data_type() {
+        return format_timestamp(column, row, unit, &options.time_zone);
+    }
+    if !is_nested(column.data_type()) {
+        return array_value_to_string(column, row);
+    }
+    let mut out = String::new();
+    format_nested(&mut out, column, row, 0, options)?;
+    Ok(truncate_text(out, options.max_width))
+}
+
+/// Format the full value of an array, e.g. to copy it.
+/// Text is returned verbatim, other values are formatted without truncation.
+pub fn format_full_value(column: &ArrayRef, row: usize, options: &FormatOptions) -> Result<String> {
+    if !column.is_null(row) && is_text(column.data_type()) {
+        return array_value_to_string(column, row);
+    }
+    let options = FormatOptions {
+        max_width: None,
+        ..options.clone()
+    };
+    format_value(column, row, &options)
+}
+
+/// Get the bytes of a binary value (if the array is binary)
+fn binary_value(column: &ArrayRef, row: usize) -> Result<Option<&[u8]>> {
+    Ok(match column.data_type() {
+        DataType::Binary => Some(downcast::<BinaryArray>(column)?.value(row)),
+        DataType::LargeBinary => Some(downcast::<LargeBinaryArray>(column)?.value(row)),
+        DataType::FixedSizeBinary(_) => Some(downcast::<FixedSizeBinaryArray>(column)?.value(row)),
+        _ => None,
+    })
+}
+
+/// Format bytes as hex, e.g. \x0a1b, truncated to the preview bytes if requested
+fn format_binary(bytes: &[u8], truncate: bool) -> String {
+    let shown = if truncate {
+        std::cmp::min(bytes.len(), BINARY_PREVIEW_BYTES)
+    } else {
+        bytes.len()
+    };
+    let mut out = String::with_capacity(2 * shown + 3);
+    out.push_str("\\x");
+    for byte in &bytes[..shown] {
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    if shown < bytes.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Escape control characters visibly, e.g. a line break as \n
+fn escape_control_characters(text: &str) -> String {
+    if !text.chars().any(char::is_control) {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\x{:02x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Truncate a text with an ellipsis to a number of terminal columns (if any)
+fn truncate_text(text: String, max_width: Option<usize>) -> String {
+    let max_width = match max_width {
+        Some(max_width) => max_width,
+        None => return text,
+    };
+    let mut width = 0;
+    let mut end = None;
+    for (i, c) in text.char_indices() {
+        width += c.width().unwrap_or(0);
+        // Remember where the text ends if there is one column left for the ellipsis
+        if end.is_none() && width >= max_width {
+            end = Some(i);
+        }
+        if width > max_width {
+            let mut out = text[..end.unwrap_or(i)].to_string();
+            out.push('…');
+            return out;
+        }
+    }
+    text
+}
+
+/// Format a float with a fixed precision or in scientific notation
+fn format_float<T>(value: T, format: &NumberFormat) -> String
+where
+    T: Copy + Into<f64> + std::fmt::Display + std::fmt::LowerExp,
+{
+    let v: f64 = value.into();
+    if !v.is_finite() {
+        return value.to_string();
+    }
+    if let Some(exponent) = format.scientific_exponent {
+        let e = if v == 0.0 {
+            0
+        } else {
+            v.abs().log10().floor() as i64
+        };
+        if e.abs() >= exponent as i64 {
+            return match format.precision {
+                Some(precision) => format!("{:.*e}", precision, value),
+                None => format!("{:e}", value),
+            };
+        }
+    }
+    match format.precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    }
+}
+
+/// Insert the group separators and the decimal separator into a plain number
+fn localize_number(text: &str, format: &NumberFormat) -> String {
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text),
+    };
+    let (integer, fraction) = match unsigned.find('.') {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    // Leave NaN and infinity alone
+    if integer.is_empty() || !integer.bytes().all(|b| b.is_ascii_digit()) {
+        return text.to_string();
+    }
+    let mut out = sign.to_string();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            if let Some(separator) = format.group_separator {
+                out.push(separator);
+            }
+        }
+        out.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        out.push(format.decimal_separator);
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Format a number
+fn format_number(column: &ArrayRef, row: usize, format: &NumberFormat) -> Result<String> {
+    let text = match column.data_type() {
+        DataType::Float32 => format_float(downcast::<Float32Array>(column)?.value(row), format),
+        DataType::Float64 => format_float(downcast::<Float64Array>(column)?.value(row), format),
+        _ => array_value_to_string(column, row)?,
+    };
+    Ok(localize_number(&text, format))
+}
+
+/// Get the number of characters from the decimal separator to the end of a formatted number
+pub fn fraction_width(text: &str, format: &NumberFormat) -> usize {
+    match text.find(format.decimal_separator) {
+        Some(i) => text[i..].chars().count(),
+        None => 0,
+    }
+}
+
+/// Format a timestamp with a time zone in the display time zone, e.g. 2022-05-01T14:30:00+02:00
+fn format_timestamp(
+    column: &ArrayRef,
+    row: usize,
+    unit: &TimeUnit,
+    time_zone: &DisplayTimeZone,
+) -> Result<String> {
+    let (value, nanos_per_unit) = match unit {
+        TimeUnit::Second => (
+            downcast::<TimestampSecondArray>(column)?.value(row),
+            1_000_000_000,
+        ),
+        TimeUnit::Millisecond => (
+            downcast::<TimestampMillisecondArray>(column)?.value(row),
+            1_000_000,
+        ),
+        TimeUnit::Microsecond => (
+            downcast::<TimestampMicrosecondArray>(column)?.value(row),
+            1_000,
+        ),
+        TimeUnit::Nanosecond => (downcast::<TimestampNanosecondArray>(column)?.value(row), 1),
+    };
+    let units_per_second = 1_000_000_000 / nanos_per_unit;
+    let seconds = value.div_euclid(units_per_second);
+    let nanos = value.rem_euclid(units_per_second) * nanos_per_unit;
+    let utc = NaiveDateTime::from_timestamp_opt(seconds, nanos as u32).ok_or_else(|| {
+        ArrowError::InvalidArgumentError(format!("timestamp {} is out of range", value))
+    })?;
+    let offset = time_zone.offset_at(utc.timestamp_millis());
+    let offset = FixedOffset::east_opt(offset).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    Ok(offset
+        .from_utc_datetime(&utc)
+        .format("%Y-%m-%dT%H:%M:%S%.f%:z")
+        .to_string())
+}
+
+/// Format the elements of a list
+fn format_elements(
+    out: &mut String,
+    values: &ArrayRef,
+    depth: usize,
+    options: &FormatOptions,
+) -> Result<()> {
+    out.push('[');
+    for i in 0..std::cmp::min(values.len(), options.max_length) {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        format_nested(out, values, i, depth + 1, options)?;
+    }
+    if values.len() > options.max_length {
+        out.push_str(if options.max_length > 0 {
+            ", …"
+        } else {
+            "…"
+        });
+    }
+    out.push(']');
+    Ok(())
+}
+
+/// Format a value inside a nested value
+fn format_nested(
+    out: &mut String,
+    column: &ArrayRef,
+    row: usize,
+    depth: usize,
+    options: &FormatOptions,
+) -> Result<()> {
+    if column.is_null(row) {
+        out.push_str("null");
+        return Ok(());
+    }
+    let truncated = depth >= options.max_depth;
+    match column.data_type() {
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) if truncated => {
+            out.push_str("[…]")
+        }
+        DataType::Struct(_) | DataType::Map(_, _) if truncated => out.push_str("{…}"),
+        DataType::List(_) => {
+            let values = downcast::<ListArray>(column)?.value(row);
+            format_elements(out, &values, depth, options)?;
+        }
+        DataType::LargeList(_) => {
+            let values = downcast::<LargeListArray>(column)?.value(row);
+            format_elements(out, &values, depth, options)?;
+        }
+        DataType::FixedSizeList(_, _) => {
+            let values = downcast::<FixedSizeListArray>(column)?.value(row);
+            format_elements(out, &values, depth, options)?;
+        }
+        DataType::Struct(_) => {
+            let array = downcast::<StructArray>(column)?;
+            out.push('{');
+            let fields = array.column_names().into_iter().zip(array.columns());
+            for (i, (name, field)) in fields.enumerate() {
+                if i == options.max_length {
+                    out.push('…');
+                    break;
+                }
+                out.push_str(name);
+                out.push_str(": ");
+                format_nested(out, field, row, depth + 1, options)?;
+                if i + 1 < array.num_columns() {
+                    out.push_str(", ");
+                }
+            }
+            out.push('}');
+        }
+        DataType::Map(_, _) => {
+            let entries = downcast::<MapArray>(column)?.value(row);
+            let entries = downcast::<StructArray>(&entries)?;
+            let (keys, values) = (entries.column(0), entries.column(1));
+            out.push('{');
+            for i in 0..entries.len() {
+                if i == options.max_length {
+                    out.push('…');
+                    break;
+                }
+                format_nested(out, keys, i, depth + 1, options)?;
+                out.push_str(": ");
+                format_nested(out, values, i, depth + 1, options)?;
+                if i + 1 < entries.len() {
+                    out.push_str(", ");
+                }
+            }
+            out.push('}');
+        }
+        // A union value is shown as the value of the selected type
+        DataType::Union(_, _) => {
+            let value = downcast::<UnionArray>(column)?.value(row);
+            format_nested(out, &value, 0, depth, options)?;
+        }
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => {
+            if let Some(bytes) = binary_value(column, row)? {
+                out.push_str(&format_binary(bytes, true));
+            }
+        }
+        DataType::Utf8 => quote(out, downcast::<StringArray>(column)?.value(row)),
+        DataType::LargeUtf8 => quote(out, downcast::<LargeStringArray>(column)?.value(row)),
+        DataType::Dictionary(_, _) if is_text(column.data_type()) => {
+            quote(out, &array_value_to_string(column, row)?)
+        }
+        _ => out.push_str(&array_value_to_string(column, row)?),
+    }
+    Ok(())
+}
+
+/// Write a string as quoted JSON string
+fn quote(out: &mut String, value: &str) {
+    out.push_str(&serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{
+        DecimalBuilder, FixedSizeBinaryBuilder, Int32Array, Int32Builder, Int64Array, ListBuilder,
+        MapBuilder, StringBuilder, TimestampMillisecondArray, TimestampSecondArray,
+    };
+    use arrow::buffer::Buffer;
+    use arrow::datatypes::Field;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_format_lists() -> Result<()> {
+        let mut builder = ListBuilder::new(ListBuilder::new(Int32Builder::new(4)));
+        builder.values().values().append_slice(&[1, 2])?;
+        builder.values().append(true)?;
+        builder.values().values().append_null()?;
+        builder.values().append(true)?;
+        builder.append(true)?;
+        builder.append(false)?;
+        builder
+            .values()
+            .values()
+            .append_slice(&(0..12).collect::<Vec<_>>())?;
+        builder.values().append(true)?;
+        builder.append(true)?;
+        let array: ArrayRef = Arc::new(builder.finish());
+
+        let options = FormatOptions::default();
+        assert_eq!(format_value(&array, 0, &options)?, "[[1, 2], [null]]");
+        assert_eq!(format_value(&array, 1, &options)?, "");
+        assert_eq!(
+            format_value(&array, 2, &options)?,
+            "[[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, …]]"
+        );
+        let shallow = FormatOptions {
+            max_depth: 1,
+            max_length: 1,
+            ..options
+        };
+        assert_eq!(format_value(&array, 0, &shallow)?, "[[…], …]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_structs_and_maps() -> Result<()> {
+        let options = FormatOptions::default();
+        let structs: ArrayRef = Arc::new(StructArray::from(vec![
+            (
+                Field::new("a", DataType::Int32, true),
+                Arc::new(Int32Array::from(vec![Some(1), None])) as ArrayRef,
+            ),
+            (
+                Field::new("b", DataType::Utf8, true),
+                Arc::new(StringArray::from(vec!["x", "say \"hi\""])) as ArrayRef,
+            ),
+        ]));
+        assert_eq!(format_value(&structs, 0, &options)?, "{a: 1, b: \"x\"}");
+        assert_eq!(
+            format_value(&structs, 1, &options)?,
+            "{a: null, b: \"say \\\"hi\\\"\"}"
+        );
+
+        let mut builder = MapBuilder::new(None, StringBuilder::new(4), Int32Builder::new(4));
+        builder.keys().append_value("k1")?;
+        builder.values().append_value(1)?;
+        builder.keys().append_value("k2")?;
+        builder.values().append_null()?;
+        builder.append(true)?;
+        builder.append(true)?;
+        let maps: ArrayRef = Arc::new(builder.finish());
+        assert_eq!(
+            format_value(&maps, 0, &options)?,
+            "{\"k1\": 1, \"k2\": null}"
+        );
+        assert_eq!(format_value(&maps, 1, &options)?, "{}");
+
+        let unions: ArrayRef = Arc::new(UnionArray::try_new(
+            Buffer::from_slice_ref(&[0_i8, 1]),
+            None,
+            vec![
+                (
+                    Field::new("i", DataType::Int32, false),
+                    Arc::new(Int32Array::from(vec![7, 0])) as ArrayRef,
+                ),
+                (
+                    Field::new("s", DataType::Utf8, false),
+                    Arc::new(StringArray::from(vec!["", "u"])) as ArrayRef,
+                ),
+            ],
+        )?);
+        assert_eq!(format_value(&unions, 0, &options)?, "7");
+        assert_eq!(format_value(&unions, 1, &options)?, "\"u\"");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_numbers() -> Result<()> {
+        let german = FormatOptions {
+            numbers: NumberFormat {
+                group_separator: Some('.'),
+                decimal_separator: ',',
+                ..NumberFormat::default()
+            },
+            ..FormatOptions::default()
+        };
+        let ints: ArrayRef = Arc::new(Int64Array::from(vec![-1234567, 12, 100]));
+        assert_eq!(format_value(&ints, 0, &german)?, "-1.234.567");
+        assert_eq!(format_value(&ints, 1, &german)?, "12");
+        assert_eq!(format_value(&ints, 2, &german)?, "100");
+
+        let floats: ArrayRef = Arc::new(Float64Array::from(vec![
+            1234567.5,
+            12.25,
+            0.0000012,
+            f64::NAN,
+            2.5e20,
+        ]));
+        assert_eq!(format_value(&floats, 0, &german)?, "1.234.567,5");
+        let precise = FormatOptions {
+            numbers: NumberFormat {
+                group_separator: Some(','),
+                precision: Some(2),
+                scientific_exponent: Some(6),
+                ..NumberFormat::default()
+            },
+            ..FormatOptions::default()
+        };
+        assert_eq!(format_value(&floats, 0, &precise)?, "1.23e6");
+        assert_eq!(format_value(&floats, 1, &precise)?, "12.25");
+        assert_eq!(format_value(&floats, 2, &precise)?, "1.20e-6");
+        assert_eq!(format_value(&floats, 3, &precise)?, "NaN");
+        let plain = FormatOptions::default();
+        assert_eq!(format_value(&floats, 4, &plain)?, "250000000000000000000");
+
+        let f32s: ArrayRef = Arc::new(Float32Array::from(vec![0.1]));
+        assert_eq!(format_value(&f32s, 0, &german)?, "0,1");
+
+        let mut builder = DecimalBuilder::new(1, 10, 3);
+        builder.append_value(1234567)?;
+        let decimals: ArrayRef = Arc::new(builder.finish());
+        assert_eq!(format_value(&decimals, 0, &german)?, "1.234,567");
+        assert_eq!(fraction_width("1.234,567", &german.numbers), 4);
+        assert_eq!(fraction_width("12", &german.numbers), 0);
+        Ok(())
+    }
+
+    /// Central European Time with summer time from March 27th to October 30th 2022
+    fn berlin_offset(name: &str, ms: i64) -> Option<i32> {
+        if name != "Europe/Berlin" {
+            return None;
+        }
+        let summer = (1648342800000..1667091600000).contains(&ms);
+        Some(if summer { 7200 } else { 3600 })
+    }
+
+    #[test]
+    fn test_format_timestamps() -> Result<()> {
+        let utc = FormatOptions::default();
+        let berlin = FormatOptions {
+            time_zone: DisplayTimeZone {
+                name: "Europe/Berlin".to_string(),
+                offset: Some(berlin_offset),
+            },
+            ..FormatOptions::default()
+        };
+        let zoned: ArrayRef = Arc::new(TimestampMillisecondArray::from_vec(
+            vec![1651415400123, 1641039000000],
+            Some("UTC".to_string()),
+        ));
+        assert_eq!(
+            format_value(&zoned, 0, &utc)?,
+            "2022-05-01T14:30:00.123+00:00"
+        );
+        assert_eq!(
+            format_value(&zoned, 0, &berlin)?,
+            "2022-05-01T16:30:00.123+02:00"
+        );
+        assert_eq!(
+            format_value(&zoned, 1, &berlin)?,
+            "2022-01-01T13:10:00+01:00"
+        );
+
+        // Timestamps before 1970 and without time zone
+        let seconds: ArrayRef = Arc::new(TimestampSecondArray::from_vec(
+            vec![-1],
+            Some("UTC".to_string()),
+        ));
+        assert_eq!(
+            format_value(&seconds, 0, &berlin)?,
+            "1970-01-01T00:59:59+01:00"
+        );
+        let naive: ArrayRef = Arc::new(TimestampSecondArray::from_vec(vec![0], None));
+        assert_eq!(format_value(&naive, 0, &berlin)?, "1970-01-01 00:00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_binary_and_text() -> Result<()> {
+        let options = FormatOptions {
+            max_width: Some(10),
+            ..FormatOptions::default()
+        };
+        let bytes: Vec<u8> = (0..20).collect();
+        let binary: ArrayRef = Arc::new(BinaryArray::from(vec![&[10u8, 27][..], &bytes[..]]));
+        assert_eq!(format_value(&binary, 0, &options)?, "\\x0a1b");
+        assert_eq!(
+            format_value(&binary, 1, &options)?,
+            "\\x000102030405060708090a0b0c0d0e0f…"
+        );
+        assert_eq!(
+            format_full_value(&binary, 1, &options)?,
+            "\\x000102030405060708090a0b0c0d0e0f10111213"
+        );
+        let mut builder = FixedSizeBinaryBuilder::new(4, 2);
+        builder.append_value([0xca, 0xfe])?;
+        let fixed: ArrayRef = Arc::new(builder.finish());
+        assert_eq!(format_value(&fixed, 0, &options)?, "\\xcafe");
+
+        let text: ArrayRef = Arc::new(StringArray::from(vec![
+            "short",
+            "\tb\n\x1b",
+            "0123456789abc",
+            "日本語のテキスト",
+        ]));
+        assert_eq!(format_value(&text, 0, &options)?, "short");
+        assert_eq!(format_value(&text, 1, &options)?, "\\tb\\n\\x1b");
+        assert_eq!(format_value(&text, 2, &options)?, "012345678…");
+        assert_eq!(format_value(&text, 3, &options)?, "日本語の…");
+        assert_eq!(format_full_value(&text, 1, &options)?, "\tb\n\x1b");
+        assert_eq!(format_full_value(&text, 2, &options)?, "0123456789abc");
+
+        // Nested values are truncated as a whole
+        let mut builder = ListBuilder::new(Int32Builder::new(4));
+        builder.values().append_slice(&[1, 2, 3, 4, 5])?;
+        builder.append(true)?;
+        let list: ArrayRef = Arc::new(builder.finish());
+        assert_eq!(format_value(&list, 0, &options)?, "[1, 2, 3,…");
+        Ok(())
+    }
+}
@@ -32,7 +32,7 @@ use crate::comfy::{Cell, Row, Table};
 
 use arrow::error::Result;
 
-use arrow::util::display::array_value_to_string;
+use crate::arrow_format::{format_value, fraction_width, is_numeric, FormatOptions};
 
 ///! Create a visual representation of record batches
 pub fn pretty_format_batches(
@@ -40,30 +40,47 @@ pub fn pretty_format_batches(
     table_width: u16,
     presets: &str,
 ) -> Result<String> {
-    Ok(create_table(results, table_width, presets)?.to_string())
+    pretty_format_batches_with_options(results, table_width, presets, &FormatOptions::default())
 }
 
-fn get_column_alignment(column: &arrow::array::ArrayRef) -> comfy::CellAlignment {
+/// Create a visual representation of record batches with format options
+pub fn pretty_format_batches_with_options(
+    results: &[RecordBatch],
+    table_width: u16,
+    presets: &str,
+    options: &FormatOptions,
+) -> Result<String> {
+    Ok(create_table(results, table_width, presets, options)?.to_string())
+}
+
+pub fn get_column_alignment(column: &arrow::array::ArrayRef) -> comfy::CellAlignment {
     match column.data_type() {
-        DataType::Boolean
-        | DataType::Int8
-        | DataType::Int16
-        | DataType::Int32
-        | DataType::Int64
-        | DataType::UInt8
-        | DataType::UInt16
-        | DataType::UInt32
-        | DataType::UInt64
-        | DataType::Float16
-        | DataType::Float32
-        | DataType::Float64
-        | DataType::Decimal(_, _) => comfy::CellAlignment::Right,
+        DataType::Boolean => comfy::CellAlignment::Right,
+        data_type if is_numeric(data_type) => comfy::CellAlignment::Right,
         _ => comfy::CellAlignment::Left,
     }
 }
 
+/// Pad right-aligned numbers so that their decimal separators line up
+fn align_decimal_separators(values: &mut [Vec<String>], col: usize, options: &FormatOptions) {
+    let width = |text: &str| fraction_width(text, &options.numbers);
+    let max_width = values.iter().map(|row| width(&row[col])).max().unwrap_or(0);
+    for row in values.iter_mut() {
+        let value = &mut row[col];
+        if !value.is_empty() {
+            let padding = max_width - width(value);
+            value.push_str(&" ".repeat(padding));
+        }
+    }
+}
+
 ///! Convert a series of record batches into a table
-fn create_table(results: &[RecordBatch], table_width: u16, presets: &str) -> Result<Table> {
+fn create_table(
+    results: &[RecordBatch],
+    table_width: u16,
+    presets: &str,
+    options: &FormatOptions,
+) -> Result<Table> {
     let mut table = Table::new();
     table.load_preset(presets);
     table.set_table_width(table_width);
@@ -81,17 +98,37 @@ fn create_table(results: &[RecordBatch], table_width: u16, presets: &str) -> Res
     }
     table.set_header(Row::from(header));
 
+    let mut values = Vec::new();
     for batch in results {
         for row in 0..batch.num_rows() {
-            let mut cells = Vec::new();
-            for col in 0..batch.num_columns() {
-                let column = batch.column(col);
-                let cell = Cell::new(&array_value_to_string(column, row)?);
-                cells.push(cell.set_alignment(get_column_alignment(column)));
+            let row_values = batch
+                .columns()
+                .iter()
+                .map(|column| format_value(column, row, options))
+                .collect::<Result<Vec<_>>>()?;
+            values.push(row_values);
+        }
+    }
+    let alignments: Vec<_> = results[0]
+        .columns()
+        .iter()
+        .map(get_column_alignment)
+        .collect();
+    if options.numbers.align_decimal {
+        for (col, field) in schema.fields().iter().enumerate() {
+            if is_numeric(field.data_type()) {
+                align_decimal_separators(&mut values, col, options);
             }
-            table.add_row(Row::from(cells));
         }
     }
+    for row_values in values {
+        let cells: Vec<_> = row_values
+            .iter()
+            .zip(alignments.iter())
+            .map(|(value, alignment)| Cell::new(value).set_alignment(*alignment))
+            .collect();
+        table.add_row(Row::from(cells));
+    }
 
     Ok(table)
 }
@@ -110,6 +147,7 @@ mod tests {
 
     use super::*;
     use arrow::array::{DecimalBuilder, Int32Array};
+    use arrow::util::display::array_value_to_string;
     use std::sync::Arc;
 
     #[test]
@@ -475,4 +513,41 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_decimal_alignment() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("f", DataType::Float64, true)]));
+        let array = Arc::new(array::Float64Array::from(vec![
+            Some(1234567.5),
+            Some(12.25),
+            None,
+            Some(3.0),
+        ])) as ArrayRef;
+        let batch = RecordBatch::try_new(schema, vec![array])?;
+
+        let mut options = FormatOptions::default();
+        options.numbers.group_separator = Some(',');
+        options.numbers.align_decimal = true;
+        let table = pretty_format_batches_with_options(
+            &[batch],
+            100,
+            ASCII_BORDERS_NO_HORIZONTAL,
+            &options,
+        )?;
+        let expected = vec![
+            "+--------------+",
+            "| f            |",
+            "+==============+",
+            "| 1,234,567.5  |",
+            "|        12.25 |",
+            "|              |",
+            "|         3    |",
+            "+--------------+",
+        ];
+
+        let actual: Vec<&str> = table.lines().collect();
+        assert_eq!(expected, actual, "Actual result:\n{}", table);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,506 @@
+use crate::arrow_format::{format_value, is_numeric, FormatOptions};
+use crate::vt100;
+use arrow::array::{Array, Float64Array};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use unicode_width::UnicodeWidthStr;
+
+/// The maximum number of bars of a bar chart or bins of a histogram
+pub const MAX_BARS: usize = 50;
+/// The default number of bins of a histogram
+pub const DEFAULT_BINS: usize = 10;
+/// The maximum terminal columns of a bar label
+const MAX_LABEL_WIDTH: usize = 20;
+/// The minimum terminal columns of the plot area
+const MIN_PLOT_WIDTH: usize = 10;
+/// The smallest and the largest number of terminal rows of a line chart
+const LINE_CHART_ROWS: (usize, usize) = (8, 24);
+/// The blocks for bar lengths in eighths of a column
+const BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+/// The bits of the braille dots, indexed by dot row and dot column
+const BRAILLE_DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// The kind of a chart
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartKind {
+    /// Horizontal bars with the y values, labeled with the x values
+    Bar,
+    /// A braille line through the x and y values
+    Line,
+    /// Horizontal bars with the number of values per range
+    Histogram,
+}
+
+impl ChartKind {
+    /// Parse a chart kind, e.g. bar
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bar" => Some(ChartKind::Bar),
+            "line" => Some(ChartKind::Line),
+            "hist" | "histogram" => Some(ChartKind::Histogram),
+            _ => None,
+        }
+    }
+}
+
+/// Get the terminal rows of a line chart in a terminal
+pub fn line_chart_rows(terminal_rows: usize) -> usize {
+    (terminal_rows / 2).clamp(LINE_CHART_ROWS.0, LINE_CHART_ROWS.1)
+}
+
+/// A canvas of braille characters with 2x4 dots each
+struct BrailleCanvas {
+    /// The terminal columns
+    cols: usize,
+    /// The terminal rows
+    rows: usize,
+    /// The dots of every character
+    cells: Vec<u8>,
+}
+
+impl BrailleCanvas {
+    /// Construct an empty canvas
+    fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            cells: vec![0; cols * rows],
+        }
+    }
+
+    /// Set a dot, y grows downwards
+    fn set(&mut self, x: usize, y: usize) {
+        if x < self.cols * 2 && y < self.rows * 4 {
+            self.cells[(y / 4) * self.cols + x / 2] |= BRAILLE_DOTS[y % 4][x % 2];
+        }
+    }
+
+    /// Draw a line between two dots
+    fn line(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+        let (x1, y1) = (to.0 as isize, to.1 as isize);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+        let mut error = dx + dy;
+        loop {
+            self.set(x as usize, y as usize);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Get the characters of a row
+    fn row(&self, row: usize) -> String {
+        self.cells[row * self.cols..(row + 1) * self.cols]
+            .iter()
+            .map(|dots| std::char::from_u32(0x2800 + *dots as u32).unwrap_or(' '))
+            .collect()
+    }
+}
+
+/// Find a column of the record batches
+fn find_column(batches: &[RecordBatch], name: &str) -> Result<usize, String> {
+    batches
+        .first()
+        .and_then(|b| b.schema().index_of(name).ok())
+        .ok_or_else(|| format!("Unknown column: {}", name))
+}
+
+/// Get the values of a numeric or temporal column as floats, None for nulls
+fn numbers(batches: &[RecordBatch], index: usize, name: &str) -> Result<Vec<Option<f64>>, String> {
+    let mut values = Vec::new();
+    for batch in batches {
+        let column = batch.column(index);
+        // Temporal values are charted by their integer representation
+        let floats = match column.data_type() {
+            t if is_numeric(t) => cast(column, &DataType::Float64),
+            DataType::Date32 | DataType::Time32(_) => {
+                cast(column, &DataType::Int32).and_then(|c| cast(&c, &DataType::Float64))
+            }
+            DataType::Date64 | DataType::Time64(_) | DataType::Timestamp(_, _) => {
+                cast(column, &DataType::Int64).and_then(|c| cast(&c, &DataType::Float64))
+            }
+            _ => return Err(format!("Column {} is not numeric", name)),
+        }
+        .map_err(|e| e.to_string())?;
+        let floats = floats
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| format!("Column {} is not numeric", name))?;
+        values.extend((0..floats.len()).map(|i| {
+            if floats.is_valid(i) && floats.value(i).is_finite() {
+                Some(floats.value(i))
+            } else {
+                None
+            }
+        }));
+    }
+    Ok(values)
+}
+
+/// Get the values of a column as labels
+fn labels(batches: &[RecordBatch], index: usize, options: &FormatOptions) -> Vec<String> {
+    let options = FormatOptions {
+        max_width: Some(MAX_LABEL_WIDTH),
+        ..options.clone()
+    };
+    let mut labels = Vec::new();
+    for batch in batches {
+        let column = batch.column(index);
+        labels.extend(
+            (0..column.len()).map(|row| format_value(column, row, &options).unwrap_or_default()),
+        );
+    }
+    labels
+}
+
+/// Format an axis value with enough fraction digits for a value range, e.g. 2.50 for a range of 10
+fn format_tick(value: f64, range: f64) -> String {
+    let digits = if range > 0.0 {
+        (2 - range.log10().floor() as i32).clamp(0, 6) as usize
+    } else {
+        2
+    };
+    format!("{:.*}", digits, value)
+}
+
+/// Pad a text with spaces on the left to a number of terminal columns
+fn pad_left(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text.width())), text)
+}
+
+/// Render horizontal bars with labels and value texts
+fn render_bars(labels: &[String], values: &[f64], texts: &[String], width: usize) -> String {
+    let label_width = labels.iter().map(|l| l.width()).max().unwrap_or(0);
+    let text_width = texts.iter().map(|t| t.width()).max().unwrap_or(0);
+    let bar_width = std::cmp::max(width.saturating_sub(label_width + text_width + 3), 1);
+    // Bars start at zero or at the smallest value if it is negative
+    let low = values.iter().cloned().fold(0.0, f64::min);
+    let high = values.iter().cloned().fold(0.0, f64::max);
+    let span = if high > low { high - low } else { 1.0 };
+    let mut out = Vec::with_capacity(labels.len());
+    for ((label, value), text) in labels.iter().zip(values).zip(texts) {
+        let eighths = ((value - low) / span * (bar_width * 8) as f64).round() as usize;
+        let mut bar = BLOCKS[7].to_string().repeat(eighths / 8);
+        let partial = eighths % 8;
+        if partial > 0 {
+            bar.push(BLOCKS[partial - 1]);
+        }
+        out.push(format!(
+            "{} │{} {}",
+            pad_left(label, label_width),
+            bar,
+            text
+        ));
+    }
+    out.join(vt100::CRLF)
+}
+
+/// Render a bar chart of the y values of rows labeled with their x values
+fn render_bar_chart(
+    batches: &[RecordBatch],
+    x: &str,
+    y: &str,
+    width: usize,
+    options: &FormatOptions,
+) -> Result<String, String> {
+    let (x_index, y_index) = (find_column(batches, x)?, find_column(batches, y)?);
+    let ys = numbers(batches, y_index, y)?;
+    // The bars are labeled with the x values and show the formatted y values
+    let rows: Vec<(String, f64, String)> = labels(batches, x_index, options)
+        .into_iter()
+        .zip(ys)
+        .zip(labels(batches, y_index, options))
+        .filter_map(|((label, value), text)| value.map(|v| (label, v, text)))
+        .collect();
+    if rows.is_empty() {
+        return Err(format!("Column {} has no values", y));
+    }
+    let shown = &rows[..std::cmp::min(rows.len(), MAX_BARS)];
+    let labels: Vec<String> = shown.iter().map(|(l, _, _)| l.clone()).collect();
+    let values: Vec<f64> = shown.iter().map(|(_, v, _)| *v).collect();
+    let texts: Vec<String> = shown.iter().map(|(_, _, t)| t.clone()).collect();
+    let mut out = render_bars(&labels, &values, &texts, width);
+    if rows.len() > shown.len() {
+        out.push_str(&format!(
+            "{}Showing the first {} of {} rows",
+            vt100::CRLF,
+            shown.len(),
+            rows.len()
+        ));
+    }
+    Ok(out)
+}
+
+/// Render a histogram of the values of a column
+fn render_histogram(
+    batches: &[RecordBatch],
+    column: &str,
+    bins: usize,
+    width: usize,
+) -> Result<String, String> {
+    let index = find_column(batches, column)?;
+    let values: Vec<f64> = numbers(batches, index, column)?
+        .into_iter()
+        .flatten()
+        .collect();
+    if values.is_empty() {
+        return Err(format!("Column {} has no values", column));
+    }
+    let low = values.iter().cloned().fold(f64::MAX, f64::min);
+    let high = values.iter().cloned().fold(f64::MIN, f64::max);
+    let bins = if high > low {
+        std::cmp::max(bins, 1)
+    } else {
+        1
+    };
+    let bin_width = (high - low) / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for value in values.iter() {
+        let bin = if bin_width > 0.0 {
+            ((value - low) / bin_width).floor() as usize
+        } else {
+            0
+        };
+        counts[std::cmp::min(bin, bins - 1)] += 1;
+    }
+    // The last bin includes the largest value
+    let labels: Vec<String> = (0..bins)
+        .map(|i| {
+            let from = low + bin_width * i as f64;
+            let to = low + bin_width * (i + 1) as f64;
+            format!(
+                "[{}, {}{}",
+                format_tick(from, high - low),
+                format_tick(to, high - low),
+                if i + 1 == bins { ']' } else { ')' }
+            )
+        })
+        .collect();
+    let values: Vec<f64> = counts.iter().map(|c| *c as f64).collect();
+    let texts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+    Ok(render_bars(&labels, &values, &texts, width))
+}
+
+/// Render a braille line chart of the y values over the x values
+fn render_line_chart(
+    batches: &[RecordBatch],
+    x: &str,
+    y: &str,
+    width: usize,
+    height: usize,
+    options: &FormatOptions,
+) -> Result<String, String> {
+    let (x_index, y_index) = (find_column(batches, x)?, find_column(batches, y)?);
+    let ys = numbers(batches, y_index, y)?;
+    // Other x values are charted in the order of the rows
+    let xs = numbers(batches, x_index, x)
+        .unwrap_or_else(|_| (0..ys.len()).map(|i| Some(i as f64)).collect());
+    let mut points: Vec<(f64, f64, usize)> = xs
+        .iter()
+        .zip(ys.iter())
+        .enumerate()
+        .filter_map(|(row, (x, y))| Some(((*x)?, (*y)?, row)))
+        .collect();
+    if points.is_empty() {
+        return Err(format!("Columns {} and {} have no values", x, y));
+    }
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let x_low = points.first().map(|p| p.0).unwrap_or(0.0);
+    let x_high = points.last().map(|p| p.0).unwrap_or(0.0);
+    let y_low = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+    let y_high = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+    let y_range = y_high - y_low;
+    let ticks = [
+        format_tick(y_high, y_range),
+        format_tick((y_high + y_low) / 2.0, y_range),
+        format_tick(y_low, y_range),
+    ];
+    let tick_width = ticks.iter().map(|t| t.width()).max().unwrap_or(0);
+    let cols = width.saturating_sub(tick_width + 2);
+    if cols < MIN_PLOT_WIDTH {
+        return Err("The terminal is too narrow for a chart".to_string());
+    }
+    let rows = std::cmp::max(height, 3);
+
+    // Scale the points to dots, constant values are drawn in the middle
+    let (dots_x, dots_y) = ((cols * 2 - 1) as f64, (rows * 4 - 1) as f64);
+    let scale = |value: f64, low: f64, high: f64, dots: f64| {
+        if high > low {
+            ((value - low) / (high - low) * dots).round() as usize
+        } else {
+            (dots / 2.0).round() as usize
+        }
+    };
+    let mut canvas = BrailleCanvas::new(cols, rows);
+    let mut previous = None;
+    for (x, y, _) in points.iter() {
+        let dot = (
+            scale(*x, x_low, x_high, dots_x),
+            dots_y as usize - scale(*y, y_low, y_high, dots_y),
+        );
+        canvas.line(previous.unwrap_or(dot), dot);
+        previous = Some(dot);
+    }
+
+    let mut out = Vec::with_capacity(rows + 2);
+    for row in 0..rows {
+        let tick = match row {
+            0 => Some(&ticks[0]),
+            r if r == rows / 2 => Some(&ticks[1]),
+            r if r + 1 == rows => Some(&ticks[2]),
+            _ => None,
+        };
+        out.push(match tick {
+            Some(tick) => format!("{} ┤{}", pad_left(tick, tick_width), canvas.row(row)),
+            None => format!("{} │{}", " ".repeat(tick_width), canvas.row(row)),
+        });
+    }
+    out.push(format!("{} └{}", " ".repeat(tick_width), "─".repeat(cols)));
+
+    // Label the x axis with the first and the last x value
+    let x_labels = labels(batches, x_index, options);
+    let first = &x_labels[points.first().map(|p| p.2).unwrap_or(0)];
+    let last = &x_labels[points.last().map(|p| p.2).unwrap_or(0)];
+    let mut axis = format!("{}{}", " ".repeat(tick_width + 2), first);
+    if points.len() > 1 && first.width() + last.width() < cols {
+        axis.push_str(&pad_left(last, cols - first.width()));
+    }
+    out.push(axis);
+    Ok(out.join(vt100::CRLF))
+}
+
+/// Render a chart of the record batches of a result.
+/// The y column of histograms is the number of bins (if any).
+pub fn render_chart(
+    kind: ChartKind,
+    batches: &[RecordBatch],
+    x: &str,
+    y: Option<&str>,
+    width: usize,
+    height: usize,
+    options: &FormatOptions,
+) -> Result<String, String> {
+    if batches.is_empty() {
+        return Err("No result to chart".to_string());
+    }
+    match (kind, y) {
+        (ChartKind::Bar, Some(y)) => render_bar_chart(batches, x, y, width, options),
+        (ChartKind::Line, Some(y)) => render_line_chart(batches, x, y, width, height, options),
+        (ChartKind::Histogram, None) => render_histogram(batches, x, DEFAULT_BINS, width),
+        (ChartKind::Histogram, Some(bins)) => match bins.parse::<usize>() {
+            Ok(bins) if bins > 0 && bins <= MAX_BARS => render_histogram(batches, x, bins, width),
+            _ => Err(format!(
+                "Invalid number of bins: {}, use 1 to {}",
+                bins, MAX_BARS
+            )),
+        },
+        (_, None) => Err("The chart needs a y column".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Float64Array, Int32Array, StringArray};
+    use arrow::datatypes::{Field, Schema};
+    use std::sync::Arc;
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("day", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, true),
+            Field::new("x", DataType::Float64, false),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["mon", "tue", "wed", "thu"])),
+                Arc::new(Int32Array::from(vec![Some(8), Some(4), None, Some(2)])),
+                Arc::new(Float64Array::from(vec![0.0, 1.0, 2.0, 3.0])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn chart(kind: ChartKind, x: &str, y: Option<&str>, width: usize) -> Result<String, String> {
+        render_chart(kind, &[batch()], x, y, width, 4, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_bar_chart() {
+        let out = chart(ChartKind::Bar, "day", Some("n"), 20).unwrap();
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            vec!["mon │█████████████ 8", "tue │██████▌ 4", "thu │███▎ 2"]
+        );
+        let out = chart(ChartKind::Bar, "day", Some("x"), 10).unwrap();
+        assert_eq!(out.split("\r\n").nth(1), Some("tue │█ 1"));
+        assert_eq!(
+            chart(ChartKind::Bar, "day", Some("day"), 20),
+            Err("Column day is not numeric".to_string())
+        );
+        assert_eq!(
+            chart(ChartKind::Bar, "day", Some("missing"), 20),
+            Err("Unknown column: missing".to_string())
+        );
+        assert_eq!(
+            chart(ChartKind::Bar, "day", None, 20),
+            Err("The chart needs a y column".to_string())
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let out = chart(ChartKind::Histogram, "x", Some("3"), 30).unwrap();
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            vec![
+                "[0.00, 1.00) │███████ 1",
+                "[1.00, 2.00) │███████ 1",
+                "[2.00, 3.00] │██████████████ 2",
+            ]
+        );
+        assert!(chart(ChartKind::Histogram, "x", Some("0"), 30).is_err());
+        assert!(chart(ChartKind::Histogram, "x", Some("100000000"), 30).is_err());
+    }
+
+    #[test]
+    fn test_line_chart() {
+        let out = chart(ChartKind::Line, "x", Some("n"), 16).unwrap();
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            vec![
+                "8.00 ┤⠱⡀⠀⠀⠀⠀⠀⠀⠀⠀",
+                "     │⠀⠑⡄⠀⠀⠀⠀⠀⠀⠀",
+                "5.00 ┤⠀⠀⠘⠤⣀⠀⠀⠀⠀⠀",
+                "2.00 ┤⠀⠀⠀⠀⠀⠉⠑⠒⠤⣀",
+                "     └──────────",
+                "      0        3",
+            ]
+        );
+
+        // Text x values are charted in the order of the rows
+        let out = chart(ChartKind::Line, "day", Some("x"), 16).unwrap();
+        assert!(out.ends_with("      mon    thu"));
+        assert_eq!(
+            chart(ChartKind::Line, "x", Some("n"), 12),
+            Err("The terminal is too narrow for a chart".to_string())
+        );
+    }
+}
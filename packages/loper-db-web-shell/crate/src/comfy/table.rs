@@ -361,6 +361,15 @@ impl Table {
             .collect()
     }
 
+    /// Return the content width of each column after the content arrangement.\
+    /// This allows other layouts to share the column widths of the table.
+    pub fn column_content_widths(&self) -> Vec<u16> {
+        arrange_content(self)
+            .iter()
+            .map(|info| info.content_width())
+            .collect()
+    }
+
     pub(crate) fn style_or_default(&self, component: TableComponent) -> String {
         match self.style.get(&component) {
             None => " ".to_string(),
@@ -1,6 +1,6 @@
 use super::super::cell::Cell;
 use super::super::row::Row;
-use super::super::style::CellAlignment;
+use super::super::style::{CellAlignment, Color};
 use super::super::table::Table;
 use super::column_display_info::ColumnDisplayInfo;
 use super::split::split_line;
@@ -213,26 +213,45 @@ fn pad_line(line: String, info: &ColumnDisplayInfo) -> String {
     padded_line
 }
 
-fn style_line(line: String, _cell: &Cell) -> String {
-    // XXX Maybe make xterm specific sooner or later
-
-    // let mut content = style(line);
-
-    // // Apply text color
-    // if let Some(color) = cell.fg {
-    //     content = content.with(color);
-    // }
-
-    // // Apply background color
-    // if let Some(color) = cell.bg {
-    //     content = content.on(color);
-    // }
-
-    // for attribute in cell.attributes.iter() {
-    //     content = content.attribute(*attribute);
-    // }
-
-    // content.to_string()
+fn style_line(line: String, cell: &Cell) -> String {
+    // Style the line with SGR escape sequences of xterm
+    let mut codes: Vec<String> = Vec::new();
+    if let Some(color) = cell.fg {
+        codes.push(color_code(color, false));
+    }
+    if let Some(color) = cell.bg {
+        codes.push(color_code(color, true));
+    }
+    for attribute in cell.attributes.iter() {
+        codes.push((*attribute as u8).to_string());
+    }
+    if codes.is_empty() {
+        return line;
+    }
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), line)
+}
 
-    line
+/// Get the SGR parameters of a foreground or background color
+fn color_code(color: Color, background: bool) -> String {
+    let offset = if background { 10 } else { 0 };
+    let code = match color {
+        Color::Black => 30,
+        Color::DarkRed => 31,
+        Color::DarkGreen => 32,
+        Color::DarkYellow => 33,
+        Color::DarkBlue => 34,
+        Color::DarkMagenta => 35,
+        Color::DarkCyan => 36,
+        Color::Grey => 37,
+        Color::Red => 91,
+        Color::Green => 92,
+        Color::Yellow => 93,
+        Color::Blue => 94,
+        Color::Magenta => 95,
+        Color::Cyan => 96,
+        Color::White => 97,
+        Color::Rgb { r, g, b } => return format!("{};2;{};{};{}", 38 + offset, r, g, b),
+        Color::AnsiValue(value) => return format!("{};5;{}", 38 + offset, value),
+    };
+    (code + offset).to_string()
 }
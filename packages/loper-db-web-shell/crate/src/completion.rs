@@ -0,0 +1,303 @@
+use crate::loper::tokens::{ScriptTokens, TokenType, SQL_KEYWORDS};
+use arrow::array::{Array, StringArray};
+use arrow::record_batch::RecordBatch;
+
+/// The shell commands
+pub const SHELL_COMMANDS: &[&str] = &[
+    ".browse",
+    ".chart",
+    ".clear",
+    ".connect",
+    ".copy",
+    ".diff",
+    ".find",
+    ".help",
+    ".history",
+    ".maxwidth",
+    ".nested",
+    ".numbers",
+    ".output",
+    ".pager",
+    ".paste",
+    ".ping",
+    ".profiles",
+    ".record",
+    ".summarize",
+    ".timer",
+    ".timezone",
+    ".transcript",
+];
+
+/// The query to load the catalog of the connected service
+pub const CATALOG_QUERY: &str = concat!(
+    "SELECT table_name, column_name FROM information_schema.columns ",
+    "ORDER BY table_name, ordinal_position"
+);
+
+/// Keywords that are followed by a table name
+const TABLE_KEYWORDS: &[&str] = &["DESCRIBE", "FROM", "INTO", "JOIN", "TABLE", "UPDATE"];
+
+/// A cached catalog of the connected service
+#[derive(Default)]
+pub struct Catalog {
+    /// The table names
+    tables: Vec<String>,
+    /// The column names as (table, column) pairs
+    columns: Vec<(String, String)>,
+}
+
+impl Catalog {
+    /// Read the catalog from the results of the catalog query
+    pub fn from_batches(batches: &[RecordBatch]) -> Self {
+        let mut catalog = Catalog::default();
+        for batch in batches {
+            if batch.num_columns() < 2 {
+                continue;
+            }
+            let tables = batch.column(0).as_any().downcast_ref::<StringArray>();
+            let columns = batch.column(1).as_any().downcast_ref::<StringArray>();
+            let (tables, columns) = match (tables, columns) {
+                (Some(t), Some(c)) => (t, c),
+                _ => continue,
+            };
+            for row in 0..batch.num_rows() {
+                if tables.is_null(row) || columns.is_null(row) {
+                    continue;
+                }
+                catalog.add_column(tables.value(row), columns.value(row));
+            }
+        }
+        catalog
+    }
+
+    /// Add a column to the catalog
+    pub fn add_column(&mut self, table: &str, column: &str) {
+        if !self.tables.iter().any(|t| t == table) {
+            self.tables.push(table.to_string());
+        }
+        self.columns.push((table.to_string(), column.to_string()));
+    }
+
+    /// Is the catalog empty?
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+/// What kind of object is completed
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// A shell command
+    Command,
+    /// A table name
+    Table,
+    /// A column name of a qualified table
+    Column(String),
+    /// Keywords, tables and columns
+    Any,
+}
+
+/// A completion of the word under the cursor
+#[derive(Debug)]
+pub struct Completion {
+    /// The begin of the completed word
+    pub begin: usize,
+    /// The end of the completed word
+    pub end: usize,
+    /// The completed word prefix
+    pub prefix: String,
+    /// The candidates
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    /// Get the longest common prefix of all candidates
+    pub fn common_prefix(&self) -> String {
+        let mut iter = self.candidates.iter();
+        let mut common: Vec<char> = match iter.next() {
+            Some(first) => first.chars().collect(),
+            None => return String::new(),
+        };
+        for candidate in iter {
+            let n = common
+                .iter()
+                .zip(candidate.chars())
+                .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                .count();
+            common.truncate(n);
+        }
+        common.into_iter().collect()
+    }
+}
+
+/// Does a candidate start with a prefix (ignoring the case)?
+fn matches_prefix(candidate: &str, prefix: &str) -> bool {
+    candidate.len() >= prefix.len()
+        && candidate.is_char_boundary(prefix.len())
+        && candidate[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// Is an identifier character?
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Find the completion context of the word between begin and the cursor
+fn find_context(chars: &[char], begin: usize, tokens: &ScriptTokens) -> CompletionContext {
+    // Qualified column name?
+    if begin > 0 && chars[begin - 1] == '.' {
+        let end = begin - 1;
+        let mut start = end;
+        while start > 0 && is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        if start < end {
+            return CompletionContext::Column(chars[start..end].iter().collect());
+        }
+    }
+    // Find the previous keyword
+    for t in (0..tokens.offsets.len()).rev() {
+        let ofs = tokens.offsets[t] as usize;
+        if ofs >= begin || tokens.types[t] != TokenType::Keyword {
+            continue;
+        }
+        let word: String = chars[ofs..]
+            .iter()
+            .take_while(|c| is_word_char(**c))
+            .collect();
+        let word = word.to_ascii_uppercase();
+        if TABLE_KEYWORDS.contains(&word.as_str()) {
+            return CompletionContext::Table;
+        }
+        break;
+    }
+    CompletionContext::Any
+}
+
+/// Complete the word under the cursor.
+/// Returns None if there is no word to complete.
+pub fn complete(
+    text: &str,
+    cursor: usize,
+    tokens: &ScriptTokens,
+    catalog: &Catalog,
+) -> Option<Completion> {
+    let chars: Vec<char> = text.chars().collect();
+    let cursor = std::cmp::min(cursor, chars.len());
+
+    // Find the word before the cursor
+    let mut begin = cursor;
+    while begin > 0 && is_word_char(chars[begin - 1]) {
+        begin -= 1;
+    }
+
+    // Shell command?
+    let first = chars.iter().position(|c| !c.is_whitespace());
+    let context = match first {
+        Some(f) if chars[f] == '.' => {
+            if begin != f + 1 || chars[f..cursor].iter().any(|c| c.is_whitespace()) {
+                return None;
+            }
+            begin = f;
+            CompletionContext::Command
+        }
+        _ => find_context(&chars, begin, tokens),
+    };
+
+    // Nothing to complete?
+    let prefix: String = chars[begin..cursor].iter().collect();
+    if prefix.is_empty() && !matches!(context, CompletionContext::Column(_)) {
+        return None;
+    }
+
+    // Collect the candidates
+    let mut candidates: Vec<String> = Vec::new();
+    let mut add = |candidate: &str| {
+        if matches_prefix(candidate, &prefix) && !candidates.iter().any(|c| c == candidate) {
+            candidates.push(candidate.to_string());
+        }
+    };
+    match context {
+        CompletionContext::Command => SHELL_COMMANDS.iter().for_each(|c| add(c)),
+        CompletionContext::Table => catalog.tables.iter().for_each(|t| add(t)),
+        CompletionContext::Column(ref table) => catalog
+            .columns
+            .iter()
+            .filter(|(t, _)| t.eq_ignore_ascii_case(table))
+            .for_each(|(_, c)| add(c)),
+        CompletionContext::Any => {
+            let lowercase = prefix.chars().all(|c| !c.is_uppercase());
+            for keyword in SQL_KEYWORDS.iter() {
+                if lowercase {
+                    add(&keyword.to_ascii_lowercase());
+                } else {
+                    add(keyword);
+                }
+            }
+            catalog.tables.iter().for_each(|t| add(t));
+            catalog.columns.iter().for_each(|(_, c)| add(c));
+        }
+    }
+    candidates.sort();
+    Some(Completion {
+        begin,
+        end: cursor,
+        prefix,
+        candidates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loper::tokens::tokenize;
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::default();
+        catalog.add_column("customers", "id");
+        catalog.add_column("customers", "name");
+        catalog.add_column("orders", "id");
+        catalog.add_column("orders", "customer_id");
+        catalog
+    }
+
+    fn candidates(text: &str) -> Vec<String> {
+        let tokens = tokenize(text);
+        let cursor = text.chars().count();
+        complete(text, cursor, &tokens, &catalog())
+            .map(|c| c.candidates)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_complete_keyword() {
+        assert_eq!(candidates("sel"), vec!["select"]);
+        assert_eq!(candidates("SEL"), vec!["SELECT"]);
+        assert_eq!(candidates("select * fr"), vec!["from"]);
+    }
+
+    #[test]
+    fn test_complete_command() {
+        assert_eq!(candidates(".ti"), vec![".timer", ".timezone"]);
+        assert_eq!(candidates(".timez"), vec![".timezone"]);
+        assert_eq!(candidates(".output o"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_complete_catalog() {
+        assert_eq!(candidates("select * from c"), vec!["customers"]);
+        assert_eq!(candidates("select orders.c"), vec!["customer_id"]);
+        assert_eq!(candidates("select customers."), vec!["id", "name"]);
+        assert_eq!(candidates("select cus"), vec!["customer_id", "customers"]);
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let text = "select * from orders where cust";
+        let tokens = tokenize(text);
+        let completion = complete(text, text.len(), &tokens, &catalog()).unwrap();
+        assert_eq!(completion.begin, 27);
+        assert_eq!(completion.prefix, "cust");
+        assert_eq!(completion.common_prefix(), "customer");
+    }
+}
@@ -1 +1,186 @@
+use unicode_width::UnicodeWidthChar;
+use wasm_bindgen::JsValue;
 
+const ELLIPSIS: &str = "...";
+
+/// A failed query with the fields of the error details
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryError {
+    /// The gRPC status code (if any)
+    pub code: Option<String>,
+    /// The SQLSTATE code (if any)
+    pub sqlstate: Option<String>,
+    /// The primary message
+    pub message: String,
+    /// Additional details (if any)
+    pub detail: Option<String>,
+    /// A hint how to fix the error (if any)
+    pub hint: Option<String>,
+    /// The 1-based character position of the error in the query (if any)
+    pub position: Option<usize>,
+}
+
+/// The line of a query that contains an error position
+#[derive(Debug, PartialEq, Eq)]
+pub struct ErrorLine {
+    /// The 1-based line number
+    pub line: usize,
+    /// The text of the line, shortened around the error position if necessary
+    pub text: String,
+    /// The column of the error in the text
+    pub caret_column: usize,
+    /// The width of the offending token
+    pub caret_width: usize,
+}
+
+/// Get the number of terminal columns of a character in an error line
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+impl From<js_sys::Error> for QueryError {
+    fn from(e: js_sys::Error) -> Self {
+        let get = |key: &str| js_sys::Reflect::get(&e, &JsValue::from_str(key)).ok();
+        let get_string = |key: &str| {
+            get(key)
+                .and_then(|v| v.as_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            code: get_string("code"),
+            sqlstate: get_string("sqlstate"),
+            message: e.message().into(),
+            detail: get_string("detail"),
+            hint: get_string("hint"),
+            position: get("position")
+                .and_then(|v| v.as_f64())
+                .filter(|p| *p >= 1.0)
+                .map(|p| p as usize),
+        }
+    }
+}
+
+impl QueryError {
+    /// Did the query fail because the service cannot be reached?
+    pub fn is_connection_lost(&self) -> bool {
+        self.code.as_deref() == Some("Unavailable")
+    }
+
+    /// Find the line of the error position in the query.
+    /// Lines that are wider than the maximum width are shortened around the error position.
+    pub fn error_line(&self, query: &str, max_width: usize) -> Option<ErrorLine> {
+        let chars: Vec<char> = query.chars().collect();
+        let pos = self.position? - 1;
+        if pos > chars.len() {
+            return None;
+        }
+        let begin = chars[..pos]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        let end = chars[pos..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map(|p| pos + p)
+            .unwrap_or(chars.len());
+        let line: Vec<char> = chars[begin..end]
+            .iter()
+            .map(|c| if c.is_control() { ' ' } else { *c })
+            .collect();
+        let col = pos - begin;
+
+        // Underline the whole word at the position
+        let token_len = match line.get(col) {
+            Some(c) if c.is_alphanumeric() || *c == '_' => line[col..]
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '_')
+                .count(),
+            _ => 1,
+        };
+
+        // Shorten the line around the error position
+        let width = |range: &[char]| range.iter().map(|c| char_width(*c)).sum::<usize>();
+        let (mut first, mut last) = (0, line.len());
+        if width(&line) > max_width {
+            let half = max_width / 2;
+            while first < col && width(&line[first..col]) + ELLIPSIS.len() > half {
+                first += 1;
+            }
+            last = first;
+            while last < line.len() && width(&line[first..=last]) + 2 * ELLIPSIS.len() <= max_width
+            {
+                last += 1;
+            }
+        }
+        let mut text = String::new();
+        if first > 0 {
+            text.push_str(ELLIPSIS);
+        }
+        let caret_column = text.len() + width(&line[first..col]);
+        text.extend(line[first..last].iter());
+        if last < line.len() {
+            text.push_str(ELLIPSIS);
+        }
+        let token_end = std::cmp::min(col + token_len, last);
+        Some(ErrorLine {
+            line: chars[..begin].iter().filter(|c| **c == '\n').count() + 1,
+            text,
+            caret_column,
+            caret_width: std::cmp::max(width(&line[col.min(token_end)..token_end]), 1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_at(position: usize) -> QueryError {
+        QueryError {
+            message: "syntax error".to_string(),
+            position: Some(position),
+            ..QueryError::default()
+        }
+    }
+
+    #[test]
+    fn test_error_line() {
+        let line = error_at(10).error_line("select * form foo", 80).unwrap();
+        assert_eq!(
+            line,
+            ErrorLine {
+                line: 1,
+                text: "select * form foo".to_string(),
+                caret_column: 9,
+                caret_width: 4,
+            }
+        );
+        let line = error_at(21)
+            .error_line("select *\nfrom foo\nwher a = 1", 80)
+            .unwrap();
+        assert_eq!(line.line, 3);
+        assert_eq!(line.text, "wher a = 1");
+        assert_eq!(line.caret_column, 2);
+        assert_eq!(line.caret_width, 2);
+        let line = error_at(9).error_line("select 1", 80).unwrap();
+        assert_eq!(line.caret_column, 8);
+        assert_eq!(line.caret_width, 1);
+        assert!(error_at(10).error_line("select 1", 80).is_none());
+    }
+
+    #[test]
+    fn test_error_line_shortened() {
+        let query = format!(
+            "select {} from foo where {}",
+            "a, ".repeat(40),
+            "b = 1 and ".repeat(10)
+        );
+        let line = error_at(129).error_line(&query, 40).unwrap();
+        assert!(line.text.starts_with("..."));
+        assert!(line.text.ends_with("..."));
+        assert!(line.text.chars().count() <= 40);
+        assert_eq!(&line.text[line.caret_column..line.caret_column + 4], "from");
+        assert_eq!(line.caret_width, 4);
+    }
+}
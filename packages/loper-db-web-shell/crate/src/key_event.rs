@@ -7,6 +7,11 @@ pub enum Key {
     Tab,
     Backspace,
     Delete,
+    Escape,
+    Home,
+    End,
+    PageUp,
+    PageDown,
     ArrowUp,
     ArrowLeft,
     ArrowRight,
@@ -33,8 +38,13 @@ impl FromStr for Key {
             "Dead" => Ok(Key::Dead),
             "Delete" => Ok(Key::Delete),
             "Enter" => Ok(Key::Enter),
+            "End" => Ok(Key::End),
+            "Escape" => Ok(Key::Escape),
+            "Home" => Ok(Key::Home),
             "Key" => Ok(Key::Key),
             "Meta" => Ok(Key::Meta),
+            "PageDown" => Ok(Key::PageDown),
+            "PageUp" => Ok(Key::PageUp),
             "Shift" => Ok(Key::Shift),
             "Tab" => Ok(Key::Tab),
             s => {
@@ -50,12 +60,65 @@ impl FromStr for Key {
 #[derive(PartialEq, Eq)]
 pub struct KeyEvent {
     pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
 }
 
 impl KeyEvent {
     pub fn from_event(event: web_sys::KeyboardEvent) -> Self {
+        let mut key = Key::from_str(&event.key()).unwrap_or(Key::Char(' '));
+        // Option+<letter> produces a different character on macOS, use the physical key instead
+        if event.alt_key() {
+            if let Some(letter) = event.code().strip_prefix("Key") {
+                key = Key::from_str(&letter.to_lowercase()).unwrap_or(key);
+            }
+        }
         Self {
-            key: Key::from_str(&event.key()).unwrap_or(Key::Char(' ')),
+            key,
+            ctrl: event.ctrl_key() || event.meta_key(),
+            alt: event.alt_key(),
+            shift: event.shift_key(),
         }
     }
 }
+
+/// The button of a mouse report for a scroll up
+pub const MOUSE_WHEEL_UP: u32 = 64;
+/// The button of a mouse report for a scroll down
+pub const MOUSE_WHEEL_DOWN: u32 = 65;
+
+/// A mouse event reported by the terminal in SGR mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// The button, 0 is the left button
+    pub button: u32,
+    /// The terminal row, starting at 0
+    pub row: usize,
+    /// The terminal column, starting at 0
+    pub col: usize,
+    /// Was the button pressed or released?
+    pub pressed: bool,
+}
+
+impl MouseEvent {
+    /// Parse the mouse reports in the terminal data, e.g. "\x1b[<0;12;5M"
+    pub fn parse_reports(data: &str) -> Vec<Self> {
+        data.split(crate::vt100::MOUSE_REPORT_BEGIN)
+            .skip(1)
+            .filter_map(|report| {
+                let end = report.find(['M', 'm'])?;
+                let mut params = report[..end].split(';').map(|p| p.parse::<usize>().ok());
+                let button = params.next()?? as u32;
+                let col = params.next()??.checked_sub(1)?;
+                let row = params.next()??.checked_sub(1)?;
+                Some(Self {
+                    button,
+                    row,
+                    col,
+                    pressed: report[end..].starts_with('M'),
+                })
+            })
+            .collect()
+    }
+}
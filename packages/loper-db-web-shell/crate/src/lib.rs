@@ -1,15 +1,25 @@
+pub mod arrow_format;
 pub mod arrow_printer;
 pub mod arrow_reader;
+pub mod chart;
 pub mod comfy;
+pub mod completion;
 pub mod console;
 pub mod loper;
+pub mod pager;
 pub mod error;
 pub mod key_event;
 pub mod prompt_buffer;
+pub mod prompt_renderer;
+pub mod result_diff;
+pub mod result_grid;
 pub mod shell;
 pub mod shell_api;
 pub mod shell_options;
 pub mod shell_runtime;
+pub mod summary;
+pub mod terminal_search;
+pub mod transcript;
 pub mod utils;
 pub mod vt100;
 pub mod xterm;
@@ -1,5 +1,6 @@
 use super::tokens::{JsScriptTokens, ScriptTokens};
 use crate::arrow_reader::ArrowStreamReader;
+use crate::utils;
 use js_sys::Uint8Array;
 use std::sync::Arc;
 use std::sync::RwLock;
@@ -12,8 +13,15 @@ extern "C" {
 
     #[wasm_bindgen(catch, method, js_name = "disconnect")]
     async fn disconnect(this: &JsLoperServiceConnection) -> Result<JsValue, JsValue>;
-    #[wasm_bindgen(catch, method, js_name = "runQuery")]
-    async fn run_query(this: &JsLoperServiceConnection, text: &str) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch, method, js_name = "runQueryWithStats")]
+    async fn run_query_with_stats(
+        this: &JsLoperServiceConnection,
+        text: &str,
+    ) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch, method, js_name = "ping")]
+    async fn ping(this: &JsLoperServiceConnection) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(method, js_name = "setParam")]
+    fn set_param(this: &JsLoperServiceConnection, key: &str, value: &str);
 }
 
 #[wasm_bindgen]
@@ -60,6 +68,21 @@ impl LoperServiceClient {
     }
 }
 
+/// The timings and sizes of a query
+#[derive(Clone, Debug, Default)]
+pub struct QueryStats {
+    /// The milliseconds until the first chunk arrived, i.e. the server execution
+    pub first_chunk_ms: f64,
+    /// The milliseconds between the first and the last chunk
+    pub transfer_ms: f64,
+    /// The milliseconds to decode the arrow chunks
+    pub decode_ms: f64,
+    /// The number of transferred bytes
+    pub bytes: usize,
+    /// The number of record batches
+    pub batches: usize,
+}
+
 pub struct LoperServiceConnection {
     connection: JsLoperServiceConnection,
 }
@@ -78,17 +101,35 @@ impl LoperServiceConnection {
             .await?;
         Ok(())
     }
+    /// Send a trivial query to the service
+    pub async fn ping(&self) -> Result<(), js_sys::Error> {
+        self.connection.ping().await?;
+        Ok(())
+    }
+    /// Set a session parameter that is sent with every query
+    pub fn set_param(&self, key: &str, value: &str) {
+        self.connection.set_param(key, value);
+    }
     /// Run a query
     pub async fn run_query(
         &self,
         text: &str,
     ) -> Result<Vec<arrow::record_batch::RecordBatch>, js_sys::Error> {
+        Ok(self.run_query_with_stats(text).await?.0)
+    }
+    /// Run a query and measure the timings and sizes
+    pub async fn run_query_with_stats(
+        &self,
+        text: &str,
+    ) -> Result<(Vec<arrow::record_batch::RecordBatch>, QueryStats), js_sys::Error> {
         // Run the RPC
-        let js_buffers: js_sys::Array = self
-            .connection
-            .run_query(text)
-            .await?
-            .into();
+        let result = self.connection.run_query_with_stats(text).await?;
+        let get =
+            |key: &str| js_sys::Reflect::get(&result, &key.into()).unwrap_or(JsValue::UNDEFINED);
+        let js_buffers: js_sys::Array = get("buffers").into();
+        let first_chunk_ms = get("firstChunkTime").as_f64().unwrap_or(0.0);
+        let last_chunk_ms = get("lastChunkTime").as_f64().unwrap_or(0.0);
+        let decode_start = utils::now();
 
         // Copy into wasm memory 
         let mut buffers: Vec<Vec<u8>> = Vec::new();
@@ -96,8 +137,15 @@ impl LoperServiceConnection {
             let buffer: Uint8Array = js_buffer.into();
             buffers.push(buffer.to_vec());
         }
+        let mut stats = QueryStats {
+            first_chunk_ms,
+            transfer_ms: last_chunk_ms - first_chunk_ms,
+            decode_ms: 0.0,
+            bytes: buffers.iter().map(|b| b.len()).sum(),
+            batches: 0,
+        };
         if buffers.is_empty() {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), stats));
         }
 
         // Decode the arrow ipc stream
@@ -108,6 +156,8 @@ impl LoperServiceConnection {
                 out.push(batch);
             }
         }
-        Ok(out)
+        stats.decode_ms = utils::now() - decode_start;
+        stats.batches = out.len();
+        Ok((out, stats))
     }
 }
@@ -2,7 +2,7 @@ use wasm_bindgen::prelude::*;
 
 /// A token type
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenType {
     Identifier = 0,
     NumericConstant = 1,
@@ -26,6 +26,7 @@ impl From<u8> for TokenType {
 }
 
 /// Script tokens
+#[derive(Clone, Default)]
 pub struct ScriptTokens {
     pub offsets: Vec<u32>,
     pub types: Vec<TokenType>,
@@ -53,3 +54,232 @@ impl From<JsScriptTokens> for ScriptTokens {
         }
     }
 }
+
+/// SQL keywords recognized by the local tokenizer (sorted)
+#[rustfmt::skip]
+pub const SQL_KEYWORDS: &[&str] = &[
+    "ALL", "ALTER", "ANALYZE", "AND", "ANY", "AS", "ASC", "ATTACH", "BEGIN", "BETWEEN", "BIGINT",
+    "BOOLEAN", "BOTH", "BY", "CASCADE", "CASE", "CAST", "CHAR", "CHECK", "COLLATE", "COLUMN",
+    "COMMIT", "CONSTRAINT", "COPY", "CREATE", "CROSS", "CURRENT_DATE", "CURRENT_TIME",
+    "CURRENT_TIMESTAMP", "DATABASE", "DATE", "DECIMAL", "DEFAULT", "DELETE", "DESC", "DESCRIBE",
+    "DETACH", "DISTINCT", "DOUBLE", "DROP", "ELSE", "END", "EXCEPT", "EXISTS", "EXPLAIN",
+    "EXTRACT", "FALSE", "FETCH", "FILTER", "FIRST", "FLOAT", "FOLLOWING", "FOR", "FOREIGN", "FROM",
+    "FULL", "GROUP", "HAVING", "IF", "ILIKE", "IN", "INDEX", "INNER", "INSERT", "INTEGER",
+    "INTERSECT", "INTERVAL", "INTO", "IS", "JOIN", "KEY", "LAST", "LATERAL", "LEADING", "LEFT",
+    "LIKE", "LIMIT", "NATURAL", "NOT", "NULL", "NULLS", "NUMERIC", "OFFSET", "ON", "OR", "ORDER",
+    "OUTER", "OVER", "PARTITION", "PRECEDING", "PRIMARY", "RANGE", "RECURSIVE", "REFERENCES",
+    "RETURNING", "RIGHT", "ROLLBACK", "ROW", "ROWS", "SCHEMA", "SELECT", "SET", "SHOW", "SMALLINT",
+    "SOME", "TABLE", "TEMPORARY", "TEXT", "THEN", "TIME", "TIMESTAMP", "TO", "TRAILING",
+    "TRANSACTION", "TRUE", "UNBOUNDED", "UNION", "UNIQUE", "UPDATE", "USING", "VALUES", "VARCHAR",
+    "VIEW", "WHEN", "WHERE", "WINDOW", "WITH",
+];
+
+/// Is a word a SQL keyword?
+pub fn is_keyword(word: &str) -> bool {
+    SQL_KEYWORDS
+        .binary_search(&word.to_ascii_uppercase().as_str())
+        .is_ok()
+}
+
+/// Tokenize a script text locally.
+/// Offsets are character offsets into the text, whitespace is skipped.
+/// The paragraph separator that marks artificial line wraps in the prompt is treated as whitespace.
+pub fn tokenize(text: &str) -> ScriptTokens {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = ScriptTokens::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let begin = i;
+        let token_type = match c {
+            // Skip whitespace
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+
+            // Line comment
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                TokenType::Comment
+            }
+
+            // Block comment
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i = std::cmp::min(i + 2, chars.len());
+                TokenType::Comment
+            }
+
+            // String constant or quoted identifier.
+            // Quotes are escaped by doubling them.
+            '\'' | '"' => {
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some(&q) if q == c => {
+                            i += 1;
+                            if chars.get(i) != Some(&c) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                        Some(_) => i += 1,
+                        None => break,
+                    }
+                }
+                if c == '\'' {
+                    TokenType::StringConstant
+                } else {
+                    TokenType::Identifier
+                }
+            }
+
+            // Numeric constant
+            c if c.is_ascii_digit()
+                || (c == '.' && matches!(chars.get(i + 1), Some(n) if n.is_ascii_digit())) =>
+            {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        i = j;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                TokenType::NumericConstant
+            }
+
+            // Keyword or identifier
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[begin..i].iter().collect();
+                if is_keyword(&word) {
+                    TokenType::Keyword
+                } else {
+                    TokenType::Identifier
+                }
+            }
+
+            // Everything else is an operator or punctuation
+            _ => {
+                i += 1;
+                TokenType::Operator
+            }
+        };
+        tokens.offsets.push(begin as u32);
+        tokens.types.push(token_type);
+    }
+    tokens
+}
+
+/// Split a script into the complete statements terminated by a semicolon and the remaining text.
+/// Semicolons in strings, quoted identifiers and comments do not terminate a statement.
+pub fn split_statements(text: &str) -> (Vec<String>, String) {
+    let chars: Vec<char> = text.chars().collect();
+    let tokens = tokenize(text);
+    let mut statements = Vec::new();
+    let mut begin = 0;
+    for (ofs, token_type) in tokens.offsets.iter().zip(tokens.types.iter()) {
+        let ofs = *ofs as usize;
+        if *token_type != TokenType::Operator || chars[ofs] != ';' {
+            continue;
+        }
+        let statement: String = chars[begin..=ofs].iter().collect();
+        let statement = statement.trim();
+        if statement != ";" {
+            statements.push(statement.to_string());
+        }
+        begin = ofs + 1;
+    }
+    let rest: String = chars[begin..].iter().collect();
+    (statements, rest.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords_sorted() {
+        let mut sorted = SQL_KEYWORDS.to_vec();
+        sorted.sort_unstable();
+        assert_eq!(sorted, SQL_KEYWORDS);
+    }
+
+    #[test]
+    fn test_tokenize_select() {
+        let tokens = tokenize("select a, 42 from foo where b = 'x''y';");
+        assert_eq!(
+            tokens.offsets,
+            vec![0, 7, 8, 10, 13, 18, 22, 28, 30, 32, 38]
+        );
+        assert_eq!(
+            tokens.types,
+            vec![
+                TokenType::Keyword,
+                TokenType::Identifier,
+                TokenType::Operator,
+                TokenType::NumericConstant,
+                TokenType::Keyword,
+                TokenType::Identifier,
+                TokenType::Keyword,
+                TokenType::Identifier,
+                TokenType::Operator,
+                TokenType::StringConstant,
+                TokenType::Operator,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        let tokens = tokenize("-- foo\n1.5e-3 /* bar */ \"Select\"");
+        assert_eq!(tokens.offsets, vec![0, 7, 14, 24]);
+        assert_eq!(
+            tokens.types,
+            vec![
+                TokenType::Comment,
+                TokenType::NumericConstant,
+                TokenType::Comment,
+                TokenType::Identifier,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unterminated() {
+        let tokens = tokenize("select 'abc");
+        assert_eq!(tokens.offsets, vec![0, 7]);
+        assert_eq!(
+            tokens.types,
+            vec![TokenType::Keyword, TokenType::StringConstant]
+        );
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let (statements, rest) = split_statements("select 1;\nselect ';' -- ;\n;  ;select 2");
+        assert_eq!(statements, vec!["select 1;", "select ';' -- ;\n;"]);
+        assert_eq!(rest, "select 2");
+        let (statements, rest) = split_statements("select 1");
+        assert!(statements.is_empty());
+        assert_eq!(rest, "select 1");
+    }
+}
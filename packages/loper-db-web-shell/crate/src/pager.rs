@@ -0,0 +1,375 @@
+use crate::key_event::{Key, KeyEvent};
+use crate::terminal_search;
+use crate::vt100;
+use std::fmt::Write;
+use unicode_width::UnicodeWidthChar;
+
+/// What the shell should do after a key in the pager
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagerAction {
+    /// Nothing changed
+    None,
+    /// Draw the pager again
+    Redraw,
+    /// Leave the pager
+    Quit,
+}
+
+/// A pager that shows long text in the alternate screen, similar to `less -S`.
+/// Lines are not wrapped but scrolled horizontally.
+pub struct Pager {
+    /// The lines of the text
+    lines: Vec<String>,
+    /// The width of the widest line
+    width: usize,
+    /// The first visible line
+    top: usize,
+    /// The first visible column
+    left: usize,
+    /// The terminal rows
+    rows: usize,
+    /// The terminal columns
+    cols: usize,
+    /// The search text while typing after '/'
+    search_input: Option<String>,
+    /// The text of the last search
+    search: Option<String>,
+    /// A message for the status line
+    message: Option<String>,
+}
+
+/// Get the terminal columns of a text
+fn text_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+impl Pager {
+    /// Construct a pager for a text with CRLF or LF line endings
+    pub fn new(text: &str, rows: usize, cols: usize) -> Self {
+        let lines: Vec<String> = text
+            .split('\n')
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .collect();
+        let width = lines.iter().map(|l| text_width(l)).max().unwrap_or(0);
+        Self {
+            lines,
+            width,
+            top: 0,
+            left: 0,
+            rows,
+            cols,
+            search_input: None,
+            search: None,
+            message: None,
+        }
+    }
+
+    /// Does a text need a pager in a terminal?
+    pub fn is_needed(text: &str, rows: usize, cols: usize) -> bool {
+        let mut lines = 0;
+        for line in text.split('\n') {
+            lines += 1;
+            if lines >= rows || text_width(line.trim_end_matches('\r')) > cols {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Get the number of text rows, the last terminal row is the status line
+    fn page_rows(&self) -> usize {
+        std::cmp::max(self.rows, 2) - 1
+    }
+
+    /// Get the first visible line at the end of the text
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.page_rows())
+    }
+
+    /// Get the first visible column at the right end of the text
+    fn max_left(&self) -> usize {
+        self.width.saturating_sub(self.cols)
+    }
+
+    /// Scroll vertically by a number of lines
+    fn scroll(&mut self, delta: isize) {
+        let top = self.top as isize + delta;
+        self.top = std::cmp::min(std::cmp::max(top, 0) as usize, self.max_top());
+    }
+
+    /// Scroll horizontally by a number of columns
+    fn scroll_horizontal(&mut self, delta: isize) {
+        let left = self.left as isize + delta;
+        self.left = std::cmp::min(std::cmp::max(left, 0) as usize, self.max_left());
+    }
+
+    /// Show the next line at or after a line, or the previous line before it, that contains the search text
+    fn find(&mut self, from: usize, backwards: bool) {
+        let query = match self.search {
+            Some(ref query) => query.clone(),
+            None => return,
+        };
+        let matches =
+            |i: &usize| !terminal_search::text_offsets(&self.lines[*i], &query, false).is_empty();
+        let found = if backwards {
+            (0..from).rev().find(matches)
+        } else {
+            (from..self.lines.len()).find(matches)
+        };
+        let line = match found {
+            Some(line) => line,
+            None => {
+                self.message = Some("Pattern not found".to_string());
+                return;
+            }
+        };
+        self.top = line;
+        // Scroll the match into view
+        let offset = terminal_search::text_offsets(&self.lines[line], &query, false)[0];
+        let column = text_width(&self.lines[line].chars().take(offset).collect::<String>());
+        if column < self.left || column + text_width(&query) > self.left + self.cols {
+            self.left = std::cmp::min(column.saturating_sub(self.cols / 4), self.max_left());
+        }
+    }
+
+    /// Process a key while typing the search text
+    fn on_search_key(&mut self, event: &KeyEvent) -> PagerAction {
+        let input = self.search_input.as_mut().unwrap();
+        match event.key {
+            Key::Enter => {
+                let query = self.search_input.take().unwrap_or_default();
+                if !query.is_empty() {
+                    self.search = Some(query);
+                }
+                // A new search includes the first visible line
+                self.find(self.top, false);
+            }
+            Key::Escape => self.search_input = None,
+            Key::Backspace => {
+                if input.pop().is_none() {
+                    self.search_input = None;
+                }
+            }
+            Key::Char(c) if !event.ctrl && !c.is_control() => input.push(c),
+            _ => return PagerAction::None,
+        }
+        PagerAction::Redraw
+    }
+
+    /// Process a key
+    pub fn on_key(&mut self, event: &KeyEvent) -> PagerAction {
+        if self.search_input.is_some() {
+            return self.on_search_key(event);
+        }
+        self.message = None;
+        let page = self.page_rows() as isize;
+        let half_width = std::cmp::max(self.cols / 2, 1) as isize;
+        match event.key {
+            Key::Char('q') | Key::Char('Q') | Key::Escape => return PagerAction::Quit,
+            Key::Char('c') if event.ctrl => return PagerAction::Quit,
+            Key::Char(_) if event.ctrl || event.alt => return PagerAction::None,
+            Key::ArrowDown | Key::Enter | Key::Char('j') => self.scroll(1),
+            Key::ArrowUp | Key::Char('k') => self.scroll(-1),
+            Key::PageDown | Key::Char(' ') | Key::Char('f') => self.scroll(page),
+            Key::PageUp | Key::Char('b') => self.scroll(-page),
+            Key::Char('d') => self.scroll(page / 2),
+            Key::Char('u') => self.scroll(-page / 2),
+            Key::Home | Key::Char('g') => self.top = 0,
+            Key::End | Key::Char('G') => self.top = self.max_top(),
+            Key::ArrowRight | Key::Char('l') => self.scroll_horizontal(half_width),
+            Key::ArrowLeft | Key::Char('h') => self.scroll_horizontal(-half_width),
+            Key::Char('/') => self.search_input = Some(String::new()),
+            Key::Char('n') => self.find(self.top + 1, false),
+            Key::Char('N') => self.find(self.top, true),
+            _ => return PagerAction::None,
+        }
+        PagerAction::Redraw
+    }
+
+    /// Render the visible part of a line with the search matches highlighted
+    fn render_line(&self, out: &mut String, line: &str) {
+        let mut highlighted = vec![false; line.chars().count()];
+        if let Some(ref query) = self.search {
+            let len = query.chars().count();
+            for offset in terminal_search::text_offsets(line, query, false) {
+                for h in highlighted.iter_mut().skip(offset).take(len) {
+                    *h = true;
+                }
+            }
+        }
+        let (begin, end) = (self.left, self.left + self.cols);
+        let mut column = 0;
+        let mut reverse = false;
+        for (c, h) in line.chars().zip(highlighted) {
+            let width = c.width().unwrap_or(0);
+            if column >= end {
+                break;
+            }
+            if column >= begin && column + width <= end {
+                if h != reverse {
+                    out.push_str(if h {
+                        vt100::MODE_REVERSE
+                    } else {
+                        vt100::MODES_OFF
+                    });
+                    reverse = h;
+                }
+                out.push(c);
+            } else if column + width > begin && column < end {
+                // A wide character at the border
+                out.push(' ');
+            }
+            column += width;
+        }
+        if reverse {
+            out.push_str(vt100::MODES_OFF);
+        }
+    }
+
+    /// Get the text of the status line
+    fn status(&self) -> String {
+        if let Some(ref input) = self.search_input {
+            return format!("/{}", input);
+        }
+        if let Some(ref message) = self.message {
+            return message.clone();
+        }
+        let last = std::cmp::min(self.top + self.page_rows(), self.lines.len());
+        let percent = if self.lines.is_empty() {
+            100
+        } else {
+            last * 100 / self.lines.len()
+        };
+        format!(
+            "lines {}-{}/{} {}%{} (q to quit, / to search)",
+            self.top + 1,
+            last,
+            self.lines.len(),
+            percent,
+            if self.max_left() > 0 {
+                format!(", col {}/{}", self.left + 1, self.width)
+            } else {
+                String::new()
+            }
+        )
+    }
+
+    /// Render the pager, the caller switches to the alternate screen first
+    pub fn render(&self, out: &mut String) {
+        for row in 0..self.page_rows() {
+            vt100::cursor_position(out, row, 0);
+            match self.lines.get(self.top + row) {
+                Some(line) => self.render_line(out, line),
+                None => out.push('~'),
+            }
+            out.push_str(vt100::CLEAR_LINE_CURSOR_RIGHT);
+        }
+        vt100::cursor_position(out, self.page_rows(), 0);
+        let status: String = self.status().chars().take(self.cols).collect();
+        write!(
+            out,
+            "{reverse}{status}{normal}{clear}",
+            reverse = vt100::MODE_REVERSE,
+            status = status,
+            normal = vt100::MODES_OFF,
+            clear = vt100::CLEAR_LINE_CURSOR_RIGHT
+        )
+        .unwrap();
+    }
+
+    /// Get the first visible line and column
+    pub fn position(&self) -> (usize, usize) {
+        (self.top, self.left)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: Key) -> KeyEvent {
+        KeyEvent {
+            key: k,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    fn table(rows: usize) -> String {
+        let mut lines = vec![format!("│ id │ {:<40} │", "name")];
+        for i in 0..rows {
+            lines.push(format!("│ {:>2} │ value {:<34} │", i, i));
+        }
+        lines.join("\r\n")
+    }
+
+    #[test]
+    fn test_pager_scrolling() {
+        let text = table(30);
+        assert!(Pager::is_needed(&text, 10, 80));
+        assert!(!Pager::is_needed(&table(3), 10, 80));
+        assert!(Pager::is_needed(&table(3), 10, 20));
+
+        let mut pager = Pager::new(&text, 10, 20);
+        assert_eq!(pager.on_key(&key(Key::ArrowDown)), PagerAction::Redraw);
+        assert_eq!(pager.position(), (1, 0));
+        pager.on_key(&key(Key::PageDown));
+        assert_eq!(pager.position(), (10, 0));
+        pager.on_key(&key(Key::End));
+        assert_eq!(pager.position(), (22, 0));
+        pager.on_key(&key(Key::PageDown));
+        assert_eq!(pager.position(), (22, 0));
+        pager.on_key(&key(Key::PageUp));
+        assert_eq!(pager.position(), (13, 0));
+        pager.on_key(&key(Key::Home));
+        assert_eq!(pager.position(), (0, 0));
+        pager.on_key(&key(Key::ArrowUp));
+        assert_eq!(pager.position(), (0, 0));
+
+        // Horizontal scrolling stops at the widest line
+        pager.on_key(&key(Key::ArrowRight));
+        assert_eq!(pager.position(), (0, 10));
+        for _ in 0..5 {
+            pager.on_key(&key(Key::ArrowRight));
+        }
+        assert_eq!(pager.position(), (0, 29));
+        pager.on_key(&key(Key::ArrowLeft));
+        assert_eq!(pager.position(), (0, 19));
+        assert_eq!(pager.on_key(&key(Key::Char('q'))), PagerAction::Quit);
+    }
+
+    #[test]
+    fn test_pager_search() {
+        let mut pager = Pager::new(&table(30), 10, 20);
+        for k in [Key::Char('/'), Key::Char('V'), Key::Char('a')] {
+            pager.on_key(&key(k));
+        }
+        pager.on_key(&key(Key::Backspace));
+        for c in "alue 17".chars() {
+            pager.on_key(&key(Key::Char(c)));
+        }
+        pager.on_key(&key(Key::Enter));
+        assert_eq!(pager.position(), (18, 0));
+
+        let mut out = String::new();
+        pager.render(&mut out);
+        assert!(out.contains(&format!(
+            "{}value 17{}",
+            vt100::MODE_REVERSE,
+            vt100::MODES_OFF
+        )));
+
+        // Find the next and previous match
+        pager.search = Some("value 1".to_string());
+        pager.on_key(&key(Key::Char('n')));
+        assert_eq!(pager.position().0, 19);
+        pager.on_key(&key(Key::Char('N')));
+        pager.on_key(&key(Key::Char('N')));
+        assert_eq!(pager.position().0, 17);
+        pager.search = Some("missing".to_string());
+        pager.on_key(&key(Key::Char('n')));
+        assert_eq!(pager.position().0, 17);
+        assert_eq!(pager.status(), "Pattern not found");
+    }
+}
@@ -1,16 +1,218 @@
-use crate::loper::tokens::{ScriptTokens, TokenType};
 use crate::key_event::{Key, KeyEvent};
+use crate::loper::tokens::{self, ScriptTokens, TokenType};
+use crate::prompt_renderer::{PromptRenderer, RenderedLine, Style};
 use crate::vt100;
 use crate::xterm::Terminal;
 use ropey::Rope;
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::fmt::Write;
+use std::rc::Rc;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 const PROMPT_INIT: &str = "\x1b[1mloper\x1b[m> ";
 const PROMPT_ENDL: &str = "  ...> ";
 const PROMPT_WRAP: &str = "  ..>> ";
 const PROMPT_WIDTH: usize = 7;
 const TAB_WIDTH: usize = 2;
+const KILL_RING_SIZE: usize = 16;
+const UNDO_STACK_SIZE: usize = 100;
+
+/// Get the closing character of a bracket or quote that is paired automatically
+fn closing_pair(c: char) -> Option<char> {
+    match c {
+        '(' => Some(')'),
+        '\'' | '"' => Some(c),
+        _ => None,
+    }
+}
+
+/// Is a character a quote?
+fn is_quote(c: char) -> bool {
+    c == '\'' || c == '"'
+}
+
+/// Get the end of a token, excluding trailing whitespace
+fn token_end(tokens: &ScriptTokens, chars: &[char], t: usize) -> usize {
+    let begin = tokens.offsets[t] as usize;
+    let mut end = match tokens.offsets.get(t + 1) {
+        Some(next) => std::cmp::min(*next as usize, chars.len()),
+        None => chars.len(),
+    };
+    while end > begin && chars[end - 1].is_whitespace() {
+        end -= 1;
+    }
+    end
+}
+
+/// Get the brackets that are not part of strings or comments as (position, character) pairs
+fn brackets(tokens: &ScriptTokens, chars: &[char]) -> Vec<(usize, char)> {
+    tokens
+        .offsets
+        .iter()
+        .zip(tokens.types.iter())
+        .filter(|(_, t)| **t == TokenType::Operator)
+        .map(|(ofs, _)| (*ofs as usize, chars[*ofs as usize]))
+        .filter(|(_, c)| matches!(c, '(' | ')' | '[' | ']'))
+        .collect()
+}
+
+/// Find the bracket or quote that matches the one at a position
+fn find_matching(tokens: &ScriptTokens, chars: &[char], pos: usize) -> Option<usize> {
+    let t = tokens
+        .offsets
+        .iter()
+        .rposition(|ofs| (*ofs as usize) <= pos)?;
+    let begin = tokens.offsets[t] as usize;
+    let end = token_end(tokens, chars, t);
+    if pos >= end {
+        return None;
+    }
+    match tokens.types[t] {
+        // Find the matching bracket with the same kind
+        TokenType::Operator => {
+            let (open, close, forward) = match chars[pos] {
+                '(' => ('(', ')', true),
+                ')' => ('(', ')', false),
+                '[' => ('[', ']', true),
+                ']' => ('[', ']', false),
+                _ => return None,
+            };
+            let brackets = brackets(tokens, chars);
+            let idx = brackets.iter().position(|(p, _)| *p == pos)?;
+            let mut depth = 0;
+            let candidates: Box<dyn Iterator<Item = &(usize, char)>> = if forward {
+                Box::new(brackets[(idx + 1)..].iter())
+            } else {
+                Box::new(brackets[..idx].iter().rev())
+            };
+            let (inner, outer) = if forward {
+                (open, close)
+            } else {
+                (close, open)
+            };
+            for (p, c) in candidates {
+                if *c == inner {
+                    depth += 1;
+                } else if *c == outer {
+                    if depth == 0 {
+                        return Some(*p);
+                    }
+                    depth -= 1;
+                }
+            }
+            None
+        }
+        // Match the quotes of strings and quoted identifiers
+        TokenType::StringConstant | TokenType::Identifier if is_quote(chars[begin]) => {
+            let closed = end - begin >= 2 && chars[end - 1] == chars[begin];
+            match pos {
+                _ if !closed => None,
+                p if p == begin => Some(end - 1),
+                p if p == end - 1 => Some(begin),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Is a position inside a string, a quoted identifier or a comment?
+fn in_literal(tokens: &ScriptTokens, chars: &[char], pos: usize) -> bool {
+    let t = match tokens.offsets.iter().rposition(|ofs| (*ofs as usize) < pos) {
+        Some(t) => t,
+        None => return false,
+    };
+    let begin = tokens.offsets[t] as usize;
+    let end = token_end(tokens, chars, t);
+    match tokens.types[t] {
+        TokenType::StringConstant | TokenType::Identifier if is_quote(chars[begin]) => {
+            pos < end || end - begin < 2 || chars[end - 1] != chars[begin]
+        }
+        TokenType::Comment if chars[begin] == '-' => !chars[begin..pos].contains(&'\n'),
+        TokenType::Comment => pos < end || end - begin < 4 || chars[end - 2..end] != ['*', '/'],
+        _ => false,
+    }
+}
+
+/// Get the number of brackets that are open at a position
+fn open_brackets(tokens: &ScriptTokens, chars: &[char], pos: usize) -> usize {
+    let mut depth: usize = 0;
+    for (p, c) in brackets(tokens, chars) {
+        if p >= pos {
+            break;
+        }
+        match c {
+            '(' | '[' => depth += 1,
+            _ => depth = depth.saturating_sub(1),
+        }
+    }
+    depth
+}
+
+/// Is a character part of a word?
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Get the number of terminal columns of a character
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Is a character rendered together with the previous one, e.g. a combining mark?
+fn is_zero_width(c: char) -> bool {
+    c != '\n' && c != vt100::PARAGRAPH_SEPERATOR && char_width(c) == 0
+}
+
+/// Normalize a pasted text.
+/// Converts CRLF and CR line endings to LF, expands tabs and drops other control characters.
+pub fn normalize_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                out.push('\n');
+            }
+            '\n' | vt100::NEXT_LINE | vt100::PARAGRAPH_SEPERATOR => out.push('\n'),
+            '\t' => out.push_str(&" ".repeat(TAB_WIDTH)),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The kind of the last edit
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    /// No edit, e.g. a cursor movement
+    None,
+    /// A typed character
+    Typing,
+    /// Any other edit
+    Other,
+}
+
+/// A text buffer state that can be restored
+struct UndoState {
+    /// The text buffer
+    text: Rope,
+    /// The cursor
+    cursor: usize,
+}
+
+/// The characters and tokens of the text buffer
+struct TextAnalysis {
+    /// The characters of the text buffer
+    chars: Vec<char>,
+    /// The tokens of the text buffer, None for shell commands
+    tokens: Option<ScriptTokens>,
+}
 
 pub struct PromptBuffer {
     /// The pending output buffer
@@ -21,6 +223,30 @@ pub struct PromptBuffer {
     cursor: usize,
     /// The terminal width
     terminal_width: usize,
+    /// The prompt of the first line
+    prompt: String,
+    /// The width of the first line prompt
+    prompt_width: usize,
+    /// The status in front of the default prompt, e.g. the connection state
+    status: String,
+    /// The width of the status
+    status_width: usize,
+    /// The killed texts
+    kill_ring: Vec<String>,
+    /// The states before the previous edits
+    undo_stack: Vec<UndoState>,
+    /// The states before the undone edits
+    redo_stack: Vec<UndoState>,
+    /// The kind of the last edit
+    last_edit: EditKind,
+    /// The renderer that draws the prompt
+    renderer: PromptRenderer,
+    /// The tokens of the text buffer (if highlighted by the service)
+    tokens: Option<ScriptTokens>,
+    /// The highlighted bracket or quote pair (if any)
+    matching: Option<(usize, usize)>,
+    /// The analysis of the text buffer, computed once per change of the text or the tokens
+    analysis: RefCell<Option<Rc<TextAnalysis>>>,
 }
 
 impl PromptBuffer {
@@ -31,6 +257,18 @@ impl PromptBuffer {
             text_buffer: Rope::new(),
             cursor: 0,
             terminal_width: 0,
+            prompt: PROMPT_INIT.to_string(),
+            prompt_width: PROMPT_WIDTH,
+            status: String::new(),
+            status_width: 0,
+            kill_ring: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit: EditKind::None,
+            renderer: PromptRenderer::default(),
+            tokens: None,
+            matching: None,
+            analysis: RefCell::new(None),
         }
     }
 
@@ -45,26 +283,115 @@ impl PromptBuffer {
         self.output_buffer.clear();
     }
 
+    /// Set the prompt of the first line.
+    /// The prompt is shown with the next redraw, None restores the default prompt.
+    pub fn set_prompt(&mut self, prompt: Option<&str>) {
+        match prompt {
+            Some(p) => {
+                self.prompt = p.to_string();
+                self.prompt_width = p.width();
+            }
+            None => {
+                self.prompt = format!("{}{}", self.status, PROMPT_INIT);
+                self.prompt_width = self.status_width + PROMPT_WIDTH;
+            }
+        }
+    }
+
+    /// Set the status that is shown in front of the default prompt as label and color.
+    /// The status is shown once the default prompt is restored.
+    pub fn set_status(&mut self, status: Option<(&str, &str)>) {
+        match status {
+            Some((label, color)) => {
+                self.status = format!(
+                    "{color}[{label}]{normal} ",
+                    color = color,
+                    label = label,
+                    normal = vt100::MODES_OFF
+                );
+                self.status_width = label.width() + 3;
+            }
+            None => {
+                self.status.clear();
+                self.status_width = 0;
+            }
+        }
+    }
+
+    /// Get the prompt width of a line
+    fn line_prompt_width(&self, line_id: usize) -> usize {
+        if line_id == 0 {
+            self.prompt_width
+        } else {
+            PROMPT_WIDTH
+        }
+    }
+
     /// Collect as string.
     /// Erase all artificial line wraps (encoded as paragraph separator).
     pub fn collect(&mut self) -> String {
         String::from(&self.text_buffer).replace(&vt100::PARAGRAPH_SEPERATOR.to_string(), "")
     }
 
+    /// Collect as string including artificial line wraps.
+    /// Character offsets into this string match the offsets in the text buffer.
+    pub fn collect_raw(&self) -> String {
+        String::from(&self.text_buffer)
+    }
+
     /// Replace the prompt
     pub fn replace(&mut self, input: &str) {
+        self.checkpoint(EditKind::Other);
         self.reflow(|text_buffer| {
             *text_buffer = Rope::from_str(input);
         });
+        self.update_matching();
     }
 
     /// Insert a text at position
     pub fn insert_text(&mut self, s: &str) {
+        self.checkpoint(EditKind::Other);
         let pos = self.cursor;
+        let end = self.logical_position(pos) + s.chars().count();
         self.reflow(|buffer| {
             buffer.insert(pos, s);
         });
-        self.move_cursor_to(pos + s.len());
+        self.move_cursor_to(self.buffer_position(end));
+    }
+
+    /// Paste a text at the cursor.
+    /// The text is normalized first and the prompt is redrawn only once.
+    pub fn paste(&mut self, text: &str) {
+        let text = normalize_text(text);
+        if !text.is_empty() {
+            self.insert_text(&text);
+        }
+    }
+
+    /// Count the characters before a position in the text buffer, ignoring artificial line wraps
+    fn logical_position(&self, pos: usize) -> usize {
+        let wraps = self
+            .text_buffer
+            .slice(..pos)
+            .chars()
+            .filter(|c| *c == vt100::PARAGRAPH_SEPERATOR)
+            .count();
+        pos - wraps
+    }
+
+    /// Get the position in the text buffer after a number of characters, skipping artificial line wraps
+    fn buffer_position(&self, logical: usize) -> usize {
+        let mut n = 0;
+        for (i, c) in self.text_buffer.chars().enumerate() {
+            if c == vt100::PARAGRAPH_SEPERATOR {
+                continue;
+            }
+            if n == logical {
+                return i;
+            }
+            n += 1;
+        }
+        self.text_buffer.len_chars()
     }
 
     /// Reset the prompt
@@ -72,62 +399,68 @@ impl PromptBuffer {
         self.output_buffer.clear();
         self.text_buffer = Rope::new();
         self.cursor = 0;
-        write!(self.output_buffer, "{}", PROMPT_INIT).unwrap();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.last_edit = EditKind::None;
+        self.tokens = None;
+        self.matching = None;
+        *self.analysis.get_mut() = None;
+        self.set_prompt(None);
+        self.renderer.reset();
+        self.render((0, 0));
     }
 
     /// Insert a newline at the cursor.
-    /// Writes the prompt continuation string and reflows if the cursor is not at the end.
+    /// Indents the new line by the open brackets and moves a closing bracket at the cursor to its own line.
     fn insert_newline(&mut self) {
-        // Insert a newline character
-        self.text_buffer.insert_char(self.cursor, '\n');
-        write!(
-            self.output_buffer,
-            "{endl}{prompt_cont}",
-            endl = vt100::CRLF,
-            prompt_cont = PROMPT_ENDL
-        )
-        .unwrap();
-        self.cursor += 1;
-
-        // Reflow if cursor is not at end
-        let pos = self.cursor;
-        if pos != self.text_buffer.len_chars() {
-            self.reflow(|_| ());
-            self.move_cursor_to(pos);
-        }
-    }
-
-    /// Clear the output
-    fn erase_prompt(&mut self) {
-        // Is only a single line?
-        // Just clear the line and move cursor to the beginning.
-        let line_idx = self.text_buffer.char_to_line(self.cursor);
-        let line_count = self.text_buffer.len_lines();
-        if line_count == 1 {
-            self.output_buffer.push_str(vt100::CLEAR_LINE);
-            self.output_buffer.push(vt100::CR);
-            self.cursor = 0;
-            return;
+        let cursor = self.cursor;
+        let analysis = self.analysis();
+        let chars = &analysis.chars;
+        let depth = analysis
+            .tokens
+            .as_ref()
+            .map(|tokens| open_brackets(tokens, chars, cursor))
+            .unwrap_or(0);
+        let mut text = format!("\n{}", " ".repeat(depth * TAB_WIDTH));
+        let pos = self.logical_position(cursor) + text.chars().count();
+        if depth > 0 && cursor > 0 && chars[cursor - 1] == '(' && chars.get(cursor) == Some(&')') {
+            write!(text, "\n{}", " ".repeat((depth - 1) * TAB_WIDTH)).unwrap();
         }
+        self.reflow(|buffer| buffer.insert(cursor, &text));
+        self.move_cursor_to(self.buffer_position(pos));
+    }
 
-        // Move cursor to the last line
-        assert!(line_idx < line_count);
-        self.output_buffer.push(vt100::CR);
-        if (line_idx + 1) < line_count {
-            vt100::cursor_down(&mut self.output_buffer, (line_count - 1) - line_idx);
-        }
+    /// Move cursor to position in prompt text and highlight the matching bracket
+    pub fn move_cursor_to(&mut self, pos: usize) {
+        self.move_terminal_cursor(pos);
+        self.update_matching();
+    }
 
-        // Clear all lines
-        self.output_buffer.push_str(vt100::CLEAR_LINE);
-        for _ in 1..line_count {
-            self.output_buffer.push_str(vt100::CURSOR_UP);
-            self.output_buffer.push_str(vt100::CLEAR_LINE);
+    /// Highlight the bracket or quote pair at the cursor.
+    /// Redraws the prompt if the pair changed.
+    fn update_matching(&mut self) {
+        let analysis = self.analysis();
+        let chars = &analysis.chars;
+        let cursor = self.cursor;
+        let matching = analysis.tokens.as_ref().and_then(|tokens| {
+            // Prefer the character at the cursor over the one before it
+            let at = find_matching(tokens, chars, cursor).map(|m| (cursor, m));
+            at.or_else(|| {
+                let prev = cursor.checked_sub(1)?;
+                find_matching(tokens, chars, prev).map(|m| (prev, m))
+            })
+        });
+        if matching == self.matching {
+            return;
         }
-        self.cursor = 0;
+        self.matching = matching;
+        let position = self.terminal_position();
+        self.render(position);
+        self.move_terminal_cursor(cursor);
     }
 
-    /// Move cursor to position in prompt text
-    pub fn move_cursor_to(&mut self, pos: usize) {
+    /// Move the terminal cursor to position in prompt text
+    fn move_terminal_cursor(&mut self, pos: usize) {
         let src_line_id = self.text_buffer.char_to_line(self.cursor);
         let dst_line_id = self.text_buffer.char_to_line(pos);
         match src_line_id.cmp(&dst_line_id) {
@@ -139,8 +472,8 @@ impl PromptBuffer {
             }
             Ordering::Equal => {}
         }
-        let src_col = self.cursor - self.text_buffer.line_to_char(src_line_id);
-        let dst_col = pos - self.text_buffer.line_to_char(dst_line_id);
+        let src_col = self.line_prompt_width(src_line_id) + self.line_column(self.cursor);
+        let dst_col = self.line_prompt_width(dst_line_id) + self.line_column(pos);
         match src_col.cmp(&dst_col) {
             Ordering::Less => {
                 vt100::cursor_right(&mut self.output_buffer, dst_col - src_col);
@@ -158,65 +491,57 @@ impl PromptBuffer {
         self.move_cursor_to(self.text_buffer.len_chars());
     }
 
-    // Move the cursor 1 to the left
+    // Move the cursor 1 character to the left
     fn move_cursor_left(&mut self) {
-        let mut iter = self.text_buffer.chars_at(self.cursor);
-        if let Some(c) = iter.prev() {
-            match c {
-                // Move to end of previous line?
-                '\n' | vt100::PARAGRAPH_SEPERATOR => {
-                    let line_id = self.text_buffer.char_to_line(self.cursor - 1);
-                    let line = self.text_buffer.line(line_id);
-                    write!(
-                        self.output_buffer,
-                        "{rewind}{cursor_up}",
-                        rewind = vt100::CR,
-                        cursor_up = vt100::CURSOR_UP
-                    )
-                    .unwrap();
-                    vt100::cursor_right(
-                        &mut self.output_buffer,
-                        PROMPT_WIDTH + line.len_chars() - 1,
-                    );
-                }
-                // Just cursor one to the left
-                _ => write!(
-                    self.output_buffer,
-                    "{cursor_left}",
-                    cursor_left = vt100::CURSOR_LEFT
-                )
-                .unwrap(),
+        self.move_cursor_to(self.previous_cluster_begin(self.cursor));
+    }
+
+    // Move the cursor 1 character to the right
+    fn move_cursor_right(&mut self) {
+        self.move_cursor_to(self.next_cluster_end(self.cursor));
+    }
+
+    /// Get the terminal columns between the begin of the line at a position and the position.
+    /// Does not include the prompt.
+    fn line_column(&self, pos: usize) -> usize {
+        let line_id = self.text_buffer.char_to_line(pos);
+        let begin = self.text_buffer.line_to_char(line_id);
+        self.text_buffer
+            .slice(begin..pos)
+            .chars()
+            .map(char_width)
+            .sum()
+    }
+
+    /// Get the begin of the character before a position.
+    /// Combining marks are kept together with the character they belong to.
+    fn previous_cluster_begin(&self, pos: usize) -> usize {
+        let mut begin = pos;
+        let mut iter = self.text_buffer.chars_at(pos);
+        while let Some(c) = iter.prev() {
+            begin -= 1;
+            if !is_zero_width(c) {
+                break;
             }
-            self.cursor -= 1;
         }
+        begin
     }
 
-    // Move the cursor 1 to the right
-    fn move_cursor_right(&mut self) {
-        let mut iter = self.text_buffer.chars_at(self.cursor);
-        if let Some(c) = iter.next() {
-            match c {
-                // Move to beginning of previous line?
-                '\n' | vt100::PARAGRAPH_SEPERATOR => {
-                    write!(
-                        self.output_buffer,
-                        "{rewind}{cursor_down}",
-                        rewind = vt100::CR,
-                        cursor_down = vt100::CURSOR_DOWN
-                    )
-                    .unwrap();
-                    vt100::cursor_right(&mut self.output_buffer, PROMPT_WIDTH);
-                }
-                // Just cursor one to the right
-                _ => write!(
-                    self.output_buffer,
-                    "{cursor_right}",
-                    cursor_right = vt100::CURSOR_RIGHT
-                )
-                .unwrap(),
+    /// Get the end of the character after a position.
+    /// Combining marks are kept together with the character they belong to.
+    fn next_cluster_end(&self, pos: usize) -> usize {
+        let mut iter = self.text_buffer.chars_at(pos);
+        if iter.next().is_none() {
+            return pos;
+        }
+        let mut end = pos + 1;
+        for c in iter {
+            if !is_zero_width(c) {
+                break;
             }
-            self.cursor += 1;
+            end += 1;
         }
+        end
     }
 
     /// Reflow the text buffer
@@ -224,22 +549,42 @@ impl PromptBuffer {
     where
         F: Fn(&mut Rope),
     {
-        // First erase the prompt since we need a valid text buffer for clearing
-        self.erase_prompt();
+        // Remember the terminal cursor since we need the old text buffer for that
+        let position = self.terminal_position();
         // Then adjust the rope with the provided function
         modify(&mut self.text_buffer);
+        self.tokens = None;
+        self.matching = None;
+        *self.analysis.get_mut() = None;
+        // Draw the changes
+        self.render(position);
+    }
 
-        // Rebuild text and output
-        let mut reflowed_txt = String::new();
-        self.output_buffer
-            .reserve(self.output_buffer.len() + self.text_buffer.len_chars() * 2);
-        reflowed_txt.reserve(self.text_buffer.len_chars() * 3 / 2);
+    /// Get the row and column of the terminal cursor relative to the begin of the prompt
+    fn terminal_position(&self) -> (usize, usize) {
+        let row = self.text_buffer.char_to_line(self.cursor);
+        let prompt_width = self
+            .renderer
+            .prompt_width(row)
+            .unwrap_or_else(|| self.line_prompt_width(row));
+        (row, prompt_width + self.line_column(self.cursor))
+    }
 
-        // Write initial prompt
-        let mut line_length = PROMPT_WIDTH;
-        write!(&mut self.output_buffer, "{}", PROMPT_INIT).unwrap();
+    /// Recompute the line wraps and draw the changed lines.
+    /// Expects the terminal cursor at the given position and leaves the cursor at the end of the input.
+    fn render(&mut self, position: (usize, usize)) {
+        self.rewrap();
+        let lines = self.layout();
+        self.renderer
+            .render(&mut self.output_buffer, lines, position);
+        self.cursor = self.text_buffer.len_chars();
+    }
 
-        // Write all chars in the rope
+    /// Recompute the artificial line wraps
+    fn rewrap(&mut self) {
+        let mut reflowed_txt = String::new();
+        reflowed_txt.reserve(self.text_buffer.len_chars() * 3 / 2);
+        let mut line_length = self.prompt_width;
         for c in self.text_buffer.chars() {
             match c {
                 // Skip artifical line wraps
@@ -248,292 +593,903 @@ impl PromptBuffer {
                 // Preserve explicit newlines
                 '\n' => {
                     reflowed_txt.push('\n');
-                    write!(
-                        &mut self.output_buffer,
-                        "{endl}{prompt_cont}",
-                        endl = vt100::CRLF,
-                        prompt_cont = PROMPT_ENDL
-                    )
-                    .unwrap();
                     line_length = PROMPT_WIDTH;
                 }
 
-                // Write all other characters and wrap lines if necessary
+                // Wrap lines if necessary.
+                // Zero-width characters stay on the line of the character they belong to.
                 _ => {
-                    reflowed_txt.push(c);
-                    self.output_buffer.push(c);
-                    line_length += 1;
-                    if (line_length + 1) >= self.terminal_width {
+                    let width = char_width(c);
+                    if width > 0 && (line_length + width) >= self.terminal_width {
                         reflowed_txt.push(vt100::PARAGRAPH_SEPERATOR);
-                        write!(
-                            &mut self.output_buffer,
-                            "{endl}{prompt_wrap}",
-                            endl = vt100::CRLF,
-                            prompt_wrap = PROMPT_WRAP
-                        )
-                        .unwrap();
                         line_length = PROMPT_WIDTH;
                     }
+                    reflowed_txt.push(c);
+                    line_length += width;
                 }
             }
         }
+        if self.text_buffer != reflowed_txt.as_str() {
+            self.text_buffer = Rope::from_str(&reflowed_txt);
+            *self.analysis.get_mut() = None;
+        }
+    }
 
-        // Rewrite the entire prompt
-        self.text_buffer = Rope::from_str(&reflowed_txt);
-        self.cursor = self.text_buffer.len_chars();
+    /// Split the text buffer into the lines that are drawn
+    fn layout(&self) -> Vec<RenderedLine> {
+        let styles = self.styles();
+        let mut lines = vec![RenderedLine::new(&self.prompt, self.prompt_width)];
+        for (c, style) in self.text_buffer.chars().zip(styles) {
+            match c {
+                '\n' => lines.push(RenderedLine::new(PROMPT_ENDL, PROMPT_WIDTH)),
+                vt100::PARAGRAPH_SEPERATOR => {
+                    lines.push(RenderedLine::new(PROMPT_WRAP, PROMPT_WIDTH))
+                }
+                c => lines.last_mut().unwrap().cells.push((c, style)),
+            }
+        }
+        lines
     }
 
-    /// Insert a single character at the cursor.
-    /// Takes care of line wrapping, if necessary
-    fn insert_char(&mut self, c: char) {
-        // Cursor is at end?
-        // We short-circuit that case since we don't need to take care of following lines.
-        if self.cursor == self.text_buffer.len_chars() {
-            let line_id = self.text_buffer.char_to_line(self.cursor);
-            let line = match self.text_buffer.lines_at(line_id).next() {
-                Some(rope) => rope,
-                None => return,
-            };
-            if (PROMPT_WIDTH + line.len_chars() + 1) >= self.terminal_width {
-                // Insert an artificial newline as line wrap at the cursor.
-                // The rope interprets the paragraph separator as newline.
-                // We can therefore use the character as 'artificial' newline character and skip it during reflows.
-                self.text_buffer
-                    .insert_char(self.cursor, vt100::PARAGRAPH_SEPERATOR);
-                write!(
-                    self.output_buffer,
-                    "{endl}{prompt_wrap}",
-                    endl = vt100::CRLF,
-                    prompt_wrap = PROMPT_WRAP
-                )
-                .unwrap();
-                self.cursor += 1;
+    /// Get the characters and tokens of the text buffer.
+    /// The analysis is cached until the text or the tokens change, cursor moves and renders reuse it.
+    fn analysis(&self) -> Rc<TextAnalysis> {
+        if let Some(analysis) = self.analysis.borrow().as_ref() {
+            return analysis.clone();
+        }
+        let chars: Vec<char> = self.text_buffer.chars().collect();
+        let analysis = Rc::new(TextAnalysis {
+            tokens: self.script_tokens(&chars),
+            chars,
+        });
+        *self.analysis.borrow_mut() = Some(analysis.clone());
+        analysis
+    }
+
+    /// Get the tokens of the text buffer.
+    /// Uses the tokens of the service if there are any and tokenizes locally otherwise.
+    /// Shell commands are not tokenized.
+    fn script_tokens(&self, chars: &[char]) -> Option<ScriptTokens> {
+        if let Some(tokens) = &self.tokens {
+            return Some(tokens.clone());
+        }
+        let is_command = chars
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .map(|c| *c == '.')
+            .unwrap_or(true);
+        if is_command {
+            return None;
+        }
+        Some(tokens::tokenize(&chars.iter().collect::<String>()))
+    }
+
+    /// Get the highlighting styles of the characters in the text buffer
+    fn styles(&self) -> Vec<Style> {
+        let len = self.text_buffer.len_chars();
+        let analysis = self.analysis();
+        let mut styles = match &analysis.tokens {
+            Some(tokens) => Self::token_styles(tokens, &analysis.chars),
+            None => vec![Style::Plain; len],
+        };
+        if let Some((a, b)) = self.matching {
+            if a < len && b < len {
+                styles[a] = Style::Matching;
+                styles[b] = Style::Matching;
             }
-            self.text_buffer.insert_char(self.cursor, c);
-            self.output_buffer.push(c);
-            self.cursor += 1;
-        } else {
-            // Otherwise reflow since we might need new line-wraps
-            let pos = self.cursor;
-            self.reflow(|buffer| buffer.insert_char(pos, c));
-            self.move_cursor_to(pos + 1);
         }
+        styles
     }
 
-    /// Erase the previous character
-    fn erase_previous_char(&mut self) {
-        let mut iter = self.text_buffer.chars_at(self.cursor);
-        if let Some(c) = iter.prev() {
-            match c {
-                // Remove explicit newline?
-                // Removing newlines is expensive since we have to reflow the following lines.
-                '\n' => {
-                    let pos = self.cursor;
-                    self.reflow(|buffer| buffer.remove((pos - 1)..pos));
-                    self.move_cursor_to(pos - 1);
+    /// Get the highlighting styles of the characters in the text buffer from script tokens
+    fn token_styles(tokens: &ScriptTokens, chars: &[char]) -> Vec<Style> {
+        assert_eq!(tokens.offsets.len(), tokens.types.len());
+        let mut styles = vec![Style::Plain; chars.len()];
+        for t in 0..tokens.offsets.len() {
+            let token_type = tokens.types[t];
+            let style = match token_type {
+                TokenType::Keyword => Style::Keyword,
+                TokenType::NumericConstant | TokenType::StringConstant => Style::Constant,
+                TokenType::Identifier | TokenType::Comment | TokenType::Operator => continue,
+            };
+            let begin = tokens.offsets[t] as usize;
+            let end = match tokens.offsets.get(t + 1) {
+                Some(next) => *next as usize,
+                None => chars.len(),
+            };
+            let mut in_quotes = false;
+            for i in begin..std::cmp::min(end, chars.len()) {
+                let c = chars[i];
+                let quote = c == '\'' || c == '"';
+                if token_type == TokenType::StringConstant && in_quotes && quote {
+                    styles[i] = style;
+                    break;
+                } else if token_type == TokenType::StringConstant && quote {
+                    in_quotes = true;
+                } else if !in_quotes && !c.is_alphanumeric() {
+                    break;
                 }
+                styles[i] = style;
+            }
+        }
+        styles
+    }
 
-                // Previous character is an artificial line wrap?
-                // In that case, we'll delete the character before that character.
-                vt100::PARAGRAPH_SEPERATOR => {
-                    let pos = self.cursor;
-                    let begin = std::cmp::max(pos, 2) - 2;
-                    self.reflow(|buffer| buffer.remove(begin..pos));
-                    self.move_cursor_to(begin);
-                }
+    /// Get the cursor position in the text buffer
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
 
-                // In all other cases, just remove the character
-                _ => {
-                    let pos = self.cursor;
-                    if pos == self.text_buffer.len_chars() {
-                        write!(self.output_buffer, "\u{0008} \u{0008}").unwrap();
-                        self.text_buffer.remove((self.cursor - 1)..(self.cursor));
-                        self.cursor -= 1;
-                    } else {
-                        self.reflow(|buffer| buffer.remove((pos - 1)..(pos)));
-                        self.move_cursor_to(pos - 1);
-                    }
-                }
+    /// Get the cursor position in the collected text, ignoring artificial line wraps
+    pub fn logical_cursor(&self) -> usize {
+        self.logical_position(self.cursor)
+    }
+
+    /// Get the terminal row of the cursor relative to the first prompt line
+    pub fn cursor_row(&self) -> usize {
+        self.text_buffer.char_to_line(self.cursor)
+    }
+
+    /// Get the number of terminal rows of the prompt
+    pub fn rows(&self) -> usize {
+        self.text_buffer.len_lines()
+    }
+
+    /// Replace the characters between begin and end with a text and place the cursor after it.
+    /// The positions are offsets into the collected text, ignoring artificial line wraps.
+    pub fn replace_range(&mut self, begin: usize, end: usize, text: &str) {
+        self.checkpoint(EditKind::Other);
+        let target = begin + text.chars().count();
+        let (begin, end) = (self.buffer_position(begin), self.buffer_position(end));
+        self.reflow(|buffer| {
+            buffer.remove(begin..end);
+            buffer.insert(begin, text);
+        });
+        self.move_cursor_to(self.buffer_position(target));
+    }
+
+    /// Remember the state before an edit.
+    /// Consecutive typed characters are grouped into a single undo step.
+    fn checkpoint(&mut self, kind: EditKind) {
+        let coalesce = kind == EditKind::Typing && self.last_edit == EditKind::Typing;
+        self.last_edit = kind;
+        if coalesce {
+            return;
+        }
+        if let Some(top) = self.undo_stack.last() {
+            if top.text == self.text_buffer {
+                return;
             }
         }
+        self.undo_stack.push(UndoState {
+            text: self.text_buffer.clone(),
+            cursor: self.cursor,
+        });
+        if self.undo_stack.len() > UNDO_STACK_SIZE {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
     }
 
-    /// Insert 4 spaces without line wraps
-    pub fn insert_tab(&mut self) {
-        let line = self.text_buffer.char_to_line(self.cursor);
-        let col = PROMPT_WIDTH + self.cursor - self.text_buffer.line_to_char(line);
-        let ub = self.terminal_width - 1;
-        for _ in 0..(std::cmp::min(ub - col, TAB_WIDTH)) {
-            self.text_buffer.insert_char(self.cursor, ' ');
-            self.cursor += 1;
-            self.output_buffer.push(' ');
+    /// Restore a text buffer state and return the current one
+    fn restore(&mut self, state: UndoState) -> UndoState {
+        let current = UndoState {
+            text: self.text_buffer.clone(),
+            cursor: self.cursor,
+        };
+        self.reflow(|buffer| *buffer = state.text.clone());
+        self.move_cursor_to(std::cmp::min(state.cursor, self.text_buffer.len_chars()));
+        self.last_edit = EditKind::None;
+        current
+    }
+
+    /// Undo the last edit
+    pub fn undo(&mut self) {
+        if let Some(state) = self.undo_stack.pop() {
+            let current = self.restore(state);
+            self.redo_stack.push(current);
         }
     }
 
-    /// Highlight prompt as sql
-    pub fn highlight_sql(&mut self, tokens: ScriptTokens) {
-        assert_eq!(tokens.offsets.len(), tokens.types.len());
-        // Get cursor
-        let cursor = self.cursor;
-        // Erase the prompt
-        self.erase_prompt();
-        // Emit a character
-        self.output_buffer.reserve(
-            self.output_buffer.len() + (self.text_buffer.len_chars() + tokens.offsets.len()) * 2,
-        );
-        self.output_buffer.push_str(PROMPT_INIT);
-        let emit = |c: char, out: &mut String| match c {
-            '\n' => {
-                write!(
-                    out,
-                    "{endl}{prompt_endl}",
-                    endl = vt100::CRLF,
-                    prompt_endl = PROMPT_ENDL
-                )
-                .unwrap();
+    /// Redo the last undone edit
+    pub fn redo(&mut self) {
+        if let Some(state) = self.redo_stack.pop() {
+            let current = self.restore(state);
+            self.undo_stack.push(current);
+        }
+    }
+
+    /// Print text below the prompt and write the prompt again
+    pub fn print_below(&mut self, text: &str) {
+        self.move_cursor_to_end();
+        write!(
+            self.output_buffer,
+            "{endl}{text}{endl}",
+            endl = vt100::CRLF,
+            text = text
+        )
+        .unwrap();
+        self.redraw();
+    }
+
+    /// Draw the prompt again in place, e.g. after the prompt changed
+    pub fn refresh(&mut self) {
+        let pos = self.logical_position(self.cursor);
+        let position = self.terminal_position();
+        self.render(position);
+        self.move_cursor_to(self.buffer_position(pos));
+    }
+
+    /// Write the prompt again at the current terminal line, e.g. after clearing the screen
+    pub fn redraw(&mut self) {
+        let pos = self.cursor;
+        self.renderer.reset();
+        self.render((0, 0));
+        self.move_cursor_to(pos);
+    }
+
+    /// Get the begin of the line at the cursor, ignoring artificial line wraps
+    fn line_begin(&self) -> usize {
+        let mut pos = self.cursor;
+        let mut iter = self.text_buffer.chars_at(pos);
+        while let Some(c) = iter.prev() {
+            if c == '\n' {
+                break;
             }
-            vt100::PARAGRAPH_SEPERATOR => {
-                write!(
-                    out,
-                    "{endl}{prompt_wrap}",
-                    endl = vt100::CRLF,
-                    prompt_wrap = PROMPT_WRAP
-                )
-                .unwrap();
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// Get the end of the line at the cursor, ignoring artificial line wraps
+    fn line_end(&self) -> usize {
+        let mut pos = self.cursor;
+        for c in self.text_buffer.chars_at(pos) {
+            if c == '\n' {
+                break;
             }
-            c => out.push(c),
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Get the begin of the word before the cursor
+    fn previous_word_begin(&self) -> usize {
+        let mut pos = self.cursor;
+        let mut iter = self.text_buffer.chars_at(pos);
+        let mut in_word = false;
+        while let Some(c) = iter.prev() {
+            if is_word_char(c) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// Get the end of the word after the cursor
+    fn next_word_end(&self) -> usize {
+        let mut pos = self.cursor;
+        let mut in_word = false;
+        for c in self.text_buffer.chars_at(pos) {
+            if is_word_char(c) {
+                in_word = true;
+            } else if in_word {
+                break;
+            }
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Get the position at a terminal column in a line
+    fn line_position(&self, line_id: usize, col: usize) -> usize {
+        let target = col.saturating_sub(self.line_prompt_width(line_id));
+        let mut pos = self.text_buffer.line_to_char(line_id);
+        let mut width = 0;
+        for c in self.text_buffer.line(line_id).chars() {
+            let w = char_width(c);
+            if c == '\n' || c == vt100::PARAGRAPH_SEPERATOR || (w > 0 && width + w > target) {
+                break;
+            }
+            width += w;
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Move the cursor one line up.
+    /// Returns false if the cursor is in the first line.
+    pub fn move_cursor_up(&mut self) -> bool {
+        let line_id = self.text_buffer.char_to_line(self.cursor);
+        if line_id == 0 {
+            return false;
+        }
+        let col = self.line_prompt_width(line_id) + self.line_column(self.cursor);
+        self.move_cursor_to(self.line_position(line_id - 1, col));
+        true
+    }
+
+    /// Move the cursor one line down.
+    /// Returns false if the cursor is in the last line.
+    pub fn move_cursor_down(&mut self) -> bool {
+        let line_id = self.text_buffer.char_to_line(self.cursor);
+        if (line_id + 1) >= self.text_buffer.len_lines() {
+            return false;
+        }
+        let col = self.line_prompt_width(line_id) + self.line_column(self.cursor);
+        self.move_cursor_to(self.line_position(line_id + 1, col));
+        true
+    }
+
+    /// Remove the text between begin and end and remember it in the kill ring
+    fn kill(&mut self, begin: usize, end: usize) {
+        if begin >= end {
+            return;
+        }
+        let text: String = self
+            .text_buffer
+            .slice(begin..end)
+            .chars()
+            .filter(|c| *c != vt100::PARAGRAPH_SEPERATOR)
+            .collect();
+        self.kill_ring.push(text);
+        if self.kill_ring.len() > KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+        self.reflow(|buffer| buffer.remove(begin..end));
+        self.move_cursor_to(begin);
+    }
+
+    /// Insert the last killed text at the cursor
+    fn yank(&mut self) {
+        if let Some(text) = self.kill_ring.last().cloned() {
+            self.checkpoint(EditKind::Other);
+            self.insert_text(&text);
+        }
+    }
+
+    /// Type a character at the cursor.
+    /// Closes brackets and quotes automatically and types over the closing characters.
+    fn type_char(&mut self, c: char) {
+        let pos = self.cursor;
+        let analysis = self.analysis();
+        let (tokens, chars) = match &analysis.tokens {
+            Some(tokens) => (tokens, &analysis.chars),
+            None => return self.insert_char(c),
         };
-        // Iterate over tokens
-        let mut chars_iter = self.text_buffer.chars().enumerate();
-        let mut next: Option<(usize, char)> = None;
-        for t in 0..tokens.offsets.len() {
-            let token_ofs = tokens.offsets[t];
-            let token_type = tokens.types[t];
-            let next_ofs = if (t + 1) < tokens.offsets.len() {
-                tokens.offsets[t + 1]
-            } else {
-                self.text_buffer.len_chars() as u32
-            };
-            let mut in_quotes: bool = false;
-            loop {
-                let (i, c) = match next {
-                    Some(n) => {
-                        next = None;
-                        n
-                    }
-                    None => match chars_iter.next() {
-                        Some(n) => n,
-                        None => break,
-                    },
-                };
-                if (i as u32) < token_ofs {
-                    emit(c, &mut self.output_buffer);
-                    continue;
-                }
-                if (i as u32) == token_ofs {
-                    match token_type {
-                        TokenType::Keyword => {
-                            write!(
-                                self.output_buffer,
-                                "{reset}{fg}{bold}",
-                                reset = vt100::MODES_OFF,
-                                fg = vt100::COLOR_FG_GREEN,
-                                bold = vt100::MODE_BOLD
-                            )
-                            .unwrap();
-                        }
-                        TokenType::NumericConstant => {
-                            write!(
-                                self.output_buffer,
-                                "{reset}{fg}{bold}",
-                                reset = vt100::MODES_OFF,
-                                fg = vt100::COLOR_FG_MAGENTA,
-                                bold = vt100::MODE_BOLD
-                            )
-                            .unwrap();
-                        }
-                        TokenType::StringConstant => {
-                            write!(
-                                self.output_buffer,
-                                "{reset}{fg}{bold}",
-                                reset = vt100::MODES_OFF,
-                                fg = vt100::COLOR_FG_MAGENTA,
-                                bold = vt100::MODE_BOLD
-                            )
-                            .unwrap();
-                        }
-                        TokenType::Identifier => (),
-                        TokenType::Comment => (),
-                        TokenType::Operator => (),
-                    }
-                }
-                // Check exit conditions
-                match token_type {
-                    TokenType::Keyword | TokenType::NumericConstant => {
-                        if !c.is_alphanumeric() {
-                            self.output_buffer.push_str(vt100::MODES_OFF);
-                            emit(c, &mut self.output_buffer);
-                            break;
-                        }
-                    }
-                    TokenType::Identifier | TokenType::StringConstant => {
-                        if in_quotes {
-                            if c == '\'' || c == '\"' {
-                                emit(c, &mut self.output_buffer);
-                                self.output_buffer.push_str(vt100::MODES_OFF);
-                                break;
-                            }
-                        } else if c == '\'' || c == '\"' {
-                            in_quotes = true;
-                        } else if !c.is_alphanumeric() {
-                            self.output_buffer.push_str(vt100::MODES_OFF);
-                            emit(c, &mut self.output_buffer);
-                            break;
-                        }
-                    }
-                    TokenType::Comment => (),
-                    TokenType::Operator => {
-                        if c == ' ' {
-                            self.output_buffer.push_str(vt100::MODES_OFF);
-                            emit(c, &mut self.output_buffer);
-                            break;
-                        }
-                    }
-                }
+        let next = chars.get(pos).copied();
+        let prev = chars[..pos]
+            .iter()
+            .rev()
+            .find(|c| **c != vt100::PARAGRAPH_SEPERATOR)
+            .copied();
 
-                // Preserve for next token?
-                if i as u32 >= next_ofs {
-                    next = Some((i, c));
-                    break;
-                } else {
-                    emit(c, &mut self.output_buffer);
-                }
+        // Type over a closing bracket or quote
+        if next == Some(c) && (c == ')' || is_quote(c)) {
+            let closes = match find_matching(tokens, chars, pos) {
+                Some(m) => m < pos,
+                None => false,
+            };
+            if closes {
+                self.move_cursor_right();
+                return;
             }
-            self.output_buffer.push_str(vt100::MODES_OFF);
         }
-        // Write remainder
-        for (_, c) in &mut chars_iter {
-            emit(c, &mut self.output_buffer);
+
+        // Close brackets and quotes automatically
+        let closing = match closing_pair(c) {
+            Some(closing) => closing,
+            None => return self.insert_char(c),
+        };
+        let free = next
+            .map(|n| n.is_whitespace() || n == ')' || n == ',' || n == ';')
+            .unwrap_or(true);
+        let after_word = is_quote(c) && prev.map(is_word_char).unwrap_or(false);
+        if !free || after_word || in_literal(tokens, chars, pos) {
+            return self.insert_char(c);
         }
-        self.output_buffer.push_str(vt100::MODES_OFF);
-        self.cursor = self.text_buffer.len_chars();
+        let end = self.logical_position(pos) + 1;
+        self.reflow(|buffer| {
+            buffer.insert_char(pos, closing);
+            buffer.insert_char(pos, c);
+        });
+        self.move_cursor_to(self.buffer_position(end));
+    }
+
+    /// Insert a single character at the cursor.
+    /// Takes care of line wrapping, if necessary
+    fn insert_char(&mut self, c: char) {
+        let pos = self.cursor;
+        let end = self.logical_position(pos) + 1;
+        self.reflow(|buffer| buffer.insert_char(pos, c));
+        self.move_cursor_to(self.buffer_position(end));
+    }
+
+    /// Erase the previous character.
+    /// Erases both characters of an empty bracket or quote pair.
+    fn erase_previous_char(&mut self) {
+        let pos = self.cursor;
+        let mut iter = self.text_buffer.chars_at(pos);
+        let prev = iter.prev();
+        let next = self.text_buffer.chars_at(pos).next();
+        let end = match prev.and_then(closing_pair) {
+            Some(closing) if next == Some(closing) => pos + 1,
+            _ => pos,
+        };
+        let begin = match prev {
+            // Remove explicit newline
+            Some('\n') => pos - 1,
+            // Previous character is an artificial line wrap?
+            // In that case, we'll delete the character before that character.
+            Some(vt100::PARAGRAPH_SEPERATOR) => self.previous_cluster_begin(pos - 1),
+            // In all other cases, remove the character and its combining marks
+            Some(_) => self.previous_cluster_begin(pos),
+            None => return,
+        };
+        let target = self.logical_position(begin);
+        self.reflow(|buffer| buffer.remove(begin..end));
+        self.move_cursor_to(self.buffer_position(target));
+    }
+
+    /// Erase the character at the cursor
+    fn erase_next_char(&mut self) {
+        let pos = self.cursor;
+        let mut iter = self.text_buffer.chars_at(pos);
+        let end = match iter.next() {
+            // Skip artificial line wraps and delete the character after it
+            Some(vt100::PARAGRAPH_SEPERATOR) => self.next_cluster_end(pos + 1),
+            Some(_) => self.next_cluster_end(pos),
+            None => return,
+        };
+        self.reflow(|buffer| buffer.remove(pos..end));
+        self.move_cursor_to(pos);
+    }
+
+    /// Insert spaces up to the tab width without line wraps
+    pub fn insert_tab(&mut self) {
+        let line = self.text_buffer.char_to_line(self.cursor);
+        let col = self.line_prompt_width(line) + self.line_column(self.cursor);
+        let ub = self.terminal_width - 1;
+        let n = std::cmp::min(ub.saturating_sub(col), TAB_WIDTH);
+        if n == 0 {
+            return;
+        }
+        let pos = self.cursor;
+        self.reflow(|buffer| buffer.insert(pos, &" ".repeat(n)));
+        self.move_cursor_to(pos + n);
+    }
+
+    /// Highlight prompt as sql
+    pub fn highlight_sql(&mut self, tokens: ScriptTokens) {
+        let cursor = self.cursor;
+        let position = self.terminal_position();
+        self.tokens = Some(tokens);
+        *self.analysis.get_mut() = None;
+        self.render(position);
         self.move_cursor_to(cursor);
     }
 
     /// Process key event
     pub fn consume(&mut self, event: KeyEvent) {
+        // Any other key than a typed character ends the group of typed characters
+        let typed =
+            !event.ctrl && !event.alt && matches!(event.key, Key::Char(c) if !c.is_ascii_control());
+        if !typed && self.last_edit == EditKind::Typing {
+            self.last_edit = EditKind::None;
+        }
+        if event.ctrl {
+            match event.key {
+                Key::Char('a') => self.move_cursor_to(self.line_begin()),
+                Key::Char('e') => self.move_cursor_to(self.line_end()),
+                Key::Char('k') | Key::Char('u') | Key::Char('w') => {
+                    self.checkpoint(EditKind::Other);
+                    match event.key {
+                        Key::Char('k') => self.kill(self.cursor, self.line_end()),
+                        Key::Char('u') => self.kill(self.line_begin(), self.cursor),
+                        _ => self.kill(self.previous_word_begin(), self.cursor),
+                    }
+                }
+                Key::Char('y') => self.yank(),
+                Key::Char('z') | Key::Char('Z') if event.shift => self.redo(),
+                Key::Char('z') | Key::Char('_') => self.undo(),
+                _ => {}
+            }
+            return;
+        }
+        if event.alt {
+            match event.key {
+                Key::Char('b') => self.move_cursor_to(self.previous_word_begin()),
+                Key::Char('f') => self.move_cursor_to(self.next_word_end()),
+                Key::Char('d') => {
+                    self.checkpoint(EditKind::Other);
+                    self.kill(self.cursor, self.next_word_end());
+                }
+                _ => {}
+            }
+            return;
+        }
         match event.key {
-            Key::Tab => self.insert_tab(),
-            Key::Enter => self.insert_newline(),
-            Key::Backspace => self.erase_previous_char(),
+            Key::Tab | Key::Enter | Key::Backspace | Key::Delete => {
+                self.checkpoint(EditKind::Other);
+                match event.key {
+                    Key::Tab => self.insert_tab(),
+                    Key::Enter => self.insert_newline(),
+                    Key::Backspace => self.erase_previous_char(),
+                    _ => self.erase_next_char(),
+                }
+            }
+            Key::Home => self.move_cursor_to(self.line_begin()),
+            Key::End => self.move_cursor_to(self.line_end()),
             Key::ArrowLeft => self.move_cursor_left(),
             Key::ArrowRight => self.move_cursor_right(),
             Key::Char(c) => {
                 if !c.is_ascii_control() {
-                    self.insert_char(c);
+                    self.checkpoint(EditKind::Typing);
+                    self.type_char(c);
                 }
             }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_renderer::tests::Screen;
+
+    fn key(key: Key) -> KeyEvent {
+        KeyEvent {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent {
+            key: Key::Char(c),
+            ctrl: true,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    fn alt(c: char) -> KeyEvent {
+        KeyEvent {
+            key: Key::Char(c),
+            ctrl: false,
+            alt: true,
+            shift: false,
+        }
+    }
+
+    fn prompt(text: &str) -> PromptBuffer {
+        let mut buffer = PromptBuffer::default();
+        buffer.configure(80);
+        buffer.start_new();
+        buffer.replace(text);
+        buffer
+    }
+
+    #[test]
+    fn test_word_motion() {
+        let mut buffer = prompt("select foo_bar from baz");
+        buffer.consume(alt('b'));
+        assert_eq!(buffer.cursor(), 20);
+        buffer.consume(alt('b'));
+        assert_eq!(buffer.cursor(), 15);
+        buffer.consume(alt('b'));
+        assert_eq!(buffer.cursor(), 7);
+        buffer.consume(alt('f'));
+        assert_eq!(buffer.cursor(), 14);
+    }
+
+    #[test]
+    fn test_kill_and_yank() {
+        let mut buffer = prompt("select a\nfrom b");
+        buffer.consume(ctrl('w'));
+        assert_eq!(buffer.collect(), "select a\nfrom ");
+        buffer.consume(ctrl('u'));
+        assert_eq!(buffer.collect(), "select a\n");
+        buffer.consume(ctrl('y'));
+        assert_eq!(buffer.collect(), "select a\nfrom ");
+        buffer.move_cursor_to(0);
+        buffer.consume(ctrl('k'));
+        assert_eq!(buffer.collect(), "\nfrom ");
+        buffer.consume(key(Key::Delete));
+        assert_eq!(buffer.collect(), "from ");
+    }
+
+    #[test]
+    fn test_line_motion() {
+        let mut buffer = prompt("select a,\n  b\nfrom foo");
+        assert!(buffer.move_cursor_up());
+        assert_eq!(buffer.cursor(), 13);
+        assert!(buffer.move_cursor_up());
+        assert_eq!(buffer.cursor(), 3);
+        assert!(!buffer.move_cursor_up());
+        buffer.consume(key(Key::Home));
+        assert_eq!(buffer.cursor(), 0);
+        assert!(buffer.move_cursor_down());
+        assert_eq!(buffer.cursor(), 10);
+        buffer.consume(key(Key::End));
+        assert_eq!(buffer.cursor(), 13);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut buffer = prompt("select ");
+        for c in "foo".chars() {
+            buffer.consume(key(Key::Char(c)));
+        }
+        buffer.consume(key(Key::ArrowLeft));
+        buffer.consume(key(Key::Char('x')));
+        assert_eq!(buffer.collect(), "select foxo");
+        buffer.consume(ctrl('z'));
+        assert_eq!(buffer.collect(), "select foo");
+        assert_eq!(buffer.cursor(), 9);
+        buffer.consume(ctrl('z'));
+        assert_eq!(buffer.collect(), "select ");
+        assert_eq!(buffer.cursor(), 7);
+        buffer.consume(KeyEvent {
+            key: Key::Char('Z'),
+            ctrl: true,
+            alt: false,
+            shift: true,
+        });
+        assert_eq!(buffer.collect(), "select foo");
+        assert_eq!(buffer.cursor(), 9);
+        buffer.consume(key(Key::End));
+        buffer.consume(ctrl('w'));
+        assert_eq!(buffer.collect(), "select ");
+        buffer.consume(ctrl('_'));
+        assert_eq!(buffer.collect(), "select foo");
+    }
+
+    #[test]
+    fn test_paste() {
+        assert_eq!(normalize_text("a\r\n\tb\rc\x07"), "a\n  b\nc");
+        let mut buffer = PromptBuffer::default();
+        buffer.configure(20);
+        buffer.start_new();
+        buffer.replace("select  from t");
+        buffer.move_cursor_to(7);
+        buffer.paste("a_long_column_name,\r\n\tb");
+        assert_eq!(buffer.collect(), "select a_long_column_name,\n  b from t");
+        assert!(buffer.collect_raw().contains(vt100::PARAGRAPH_SEPERATOR));
+        assert_eq!(buffer.logical_position(buffer.cursor()), 30);
+        buffer.consume(ctrl('z'));
+        assert_eq!(buffer.collect(), "select  from t");
+    }
+
+    #[test]
+    fn test_replace_range() {
+        let mut buffer = PromptBuffer::default();
+        buffer.configure(20);
+        buffer.start_new();
+        buffer.replace("select customer_identifier");
+        assert!(buffer.collect_raw().contains(vt100::PARAGRAPH_SEPERATOR));
+        // The word is replaced as a whole although it is wrapped
+        buffer.replace_range(7, 26, "id");
+        assert_eq!(buffer.collect(), "select id");
+        assert_eq!(buffer.logical_cursor(), 9);
+        buffer.replace_range(7, 9, "customer_identifier");
+        assert_eq!(buffer.collect(), "select customer_identifier");
+        assert_eq!(buffer.logical_cursor(), 26);
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let mut buffer = PromptBuffer::default();
+        buffer.configure(20);
+        buffer.start_new();
+        buffer.replace("ab日本語日本語");
+        assert_eq!(buffer.collect_raw(), "ab日本語日本\u{2029}語");
+        buffer.consume(key(Key::ArrowLeft));
+        assert_eq!(buffer.cursor(), 8);
+        buffer.consume(key(Key::ArrowLeft));
+        assert_eq!(buffer.cursor(), 7);
+        assert_eq!(buffer.line_column(7), 12);
+        assert!(buffer.move_cursor_down());
+        assert_eq!(buffer.cursor(), 9);
+        assert!(buffer.move_cursor_up());
+        assert_eq!(buffer.cursor(), 2);
+    }
+
+    #[test]
+    fn test_combining_marks() {
+        let mut buffer = prompt("ae\u{301}x");
+        buffer.consume(key(Key::ArrowLeft));
+        assert_eq!(buffer.cursor(), 3);
+        buffer.consume(key(Key::ArrowLeft));
+        assert_eq!(buffer.cursor(), 1);
+        buffer.consume(key(Key::Delete));
+        assert_eq!(buffer.collect(), "ax");
+        buffer.replace("ae\u{301}");
+        buffer.consume(key(Key::Backspace));
+        assert_eq!(buffer.collect(), "a");
+    }
+
+    fn draw(buffer: &mut PromptBuffer, screen: &mut Screen) {
+        screen.write(&buffer.output_buffer);
+        buffer.output_buffer.clear();
+    }
+
+    #[test]
+    fn test_incremental_rendering() {
+        let mut buffer = PromptBuffer::default();
+        let mut screen = Screen::new();
+        buffer.configure(30);
+        buffer.start_new();
+        let mut events: Vec<KeyEvent> = "select a, b from foo where x = 'abc' and y = 42"
+            .chars()
+            .map(|c| key(Key::Char(c)))
+            .collect();
+        events.extend(vec![key(Key::Home), alt('f'), alt('f')]);
+        events.extend(", c1".chars().map(|c| key(Key::Char(c))));
+        events.extend(vec![
+            key(Key::Enter),
+            key(Key::Backspace),
+            key(Key::Backspace),
+            ctrl('w'),
+            ctrl('z'),
+            key(Key::End),
+            key(Key::Enter),
+            key(Key::Char(';')),
+        ]);
+        for event in events {
+            buffer.consume(event);
+            draw(&mut buffer, &mut screen);
+            assert_eq!(screen.cursor(), buffer.terminal_position());
+        }
+
+        // The screen matches a prompt that is drawn at once
+        let mut expected = Screen::new();
+        let mut fresh = PromptBuffer::default();
+        fresh.configure(30);
+        fresh.start_new();
+        fresh.replace(&buffer.collect());
+        draw(&mut fresh, &mut expected);
+        assert_eq!(
+            buffer.collect(),
+            "select a, c, b from foo where x = 'abc' and y = 42\n;"
+        );
+        assert_eq!(screen.text(), expected.text());
+        assert_eq!(screen.rows(), expected.rows());
+    }
+
+    #[test]
+    fn test_status_prompt() {
+        let mut buffer = PromptBuffer::default();
+        let mut screen = Screen::new();
+        buffer.configure(40);
+        buffer.start_new();
+        for c in "select 1".chars() {
+            buffer.consume(key(Key::Char(c)));
+        }
+        buffer.consume(key(Key::ArrowLeft));
+        draw(&mut buffer, &mut screen);
+
+        // The status is drawn in front of the prompt and the cursor stays in place
+        buffer.set_status(Some(("offline", vt100::COLOR_FG_RED)));
+        buffer.set_prompt(None);
+        buffer.refresh();
+        draw(&mut buffer, &mut screen);
+        assert_eq!(screen.text(), vec!["[offline] loper> select 1"]);
+        assert_eq!(screen.cursor(), (0, 24));
+        assert_eq!(screen.cursor(), buffer.terminal_position());
+
+        // The status is removed again
+        buffer.set_status(None);
+        buffer.set_prompt(None);
+        buffer.refresh();
+        draw(&mut buffer, &mut screen);
+        assert_eq!(screen.text(), vec!["loper> select 1"]);
+        assert_eq!(screen.cursor(), (0, 14));
+    }
+
+    #[test]
+    fn test_render_bytes_per_keystroke() {
+        let mut buffer = PromptBuffer::default();
+        buffer.configure(100);
+        buffer.start_new();
+        let query: Vec<String> = (0..200)
+            .map(|i| format!("select a{0}, b{0} from t{0} where c{0} = 'v{0}'", i))
+            .collect();
+        buffer.replace(&query.join("\n"));
+        buffer.move_cursor_to(buffer.text_buffer.line_to_char(100));
+
+        // Redrawing the whole prompt is what every keystroke cost before
+        buffer.output_buffer.clear();
+        buffer.redraw();
+        let redraw_bytes = buffer.output_buffer.len();
+        buffer.output_buffer.clear();
+
+        let typed = "select 42, ";
+        let mut keystroke_bytes = 0;
+        for c in typed.chars() {
+            buffer.consume(key(Key::Char(c)));
+            keystroke_bytes += buffer.output_buffer.len();
+            buffer.output_buffer.clear();
+        }
+        let bytes_per_keystroke = keystroke_bytes / typed.len();
+        assert!(bytes_per_keystroke * 100 < redraw_bytes);
+    }
+
+    #[test]
+    fn test_bracket_matching() {
+        let mut buffer = prompt("select (a + (b)) from t where c = ')'");
+        buffer.move_cursor_to(7);
+        assert_eq!(buffer.matching, Some((7, 15)));
+        buffer.move_cursor_to(15);
+        assert_eq!(buffer.matching, Some((15, 7)));
+        buffer.move_cursor_to(14);
+        assert_eq!(buffer.matching, Some((14, 12)));
+        buffer.move_cursor_to(20);
+        assert_eq!(buffer.matching, None);
+        buffer.move_cursor_to_end();
+        assert_eq!(buffer.matching, Some((36, 34)));
+        let styles = buffer.styles();
+        assert_eq!(styles[34], Style::Matching);
+        assert_eq!(styles[35], Style::Constant);
+
+        // Cursor moves reuse the tokens, edits tokenize again
+        let analysis = buffer.analysis();
+        buffer.move_cursor_to(7);
+        assert!(Rc::ptr_eq(&analysis, &buffer.analysis()));
+        buffer.consume(key(Key::Char('x')));
+        assert!(!Rc::ptr_eq(&analysis, &buffer.analysis()));
+    }
+
+    #[test]
+    fn test_auto_pairing() {
+        let mut buffer = prompt("select ");
+        for c in "count(".chars() {
+            buffer.consume(key(Key::Char(c)));
+        }
+        assert_eq!(buffer.collect(), "select count()");
+        for c in "'a".chars() {
+            buffer.consume(key(Key::Char(c)));
+        }
+        assert_eq!(buffer.collect(), "select count('a')");
+        buffer.consume(key(Key::Char('\'')));
+        buffer.consume(key(Key::Char(')')));
+        assert_eq!(buffer.collect(), "select count('a')");
+        assert_eq!(buffer.cursor(), 17);
+
+        // No pairing inside strings and after words
+        let mut buffer = prompt("select 'it");
+        buffer.consume(key(Key::Char('(')));
+        buffer.consume(key(Key::Char('\'')));
+        assert_eq!(buffer.collect(), "select 'it('");
+
+        // Empty pairs are erased together
+        let mut buffer = prompt("select ");
+        buffer.consume(key(Key::Char('(')));
+        buffer.consume(key(Key::Backspace));
+        assert_eq!(buffer.collect(), "select ");
+    }
+
+    #[test]
+    fn test_smart_indentation() {
+        let mut buffer = prompt("select * from t where a in ");
+        buffer.consume(key(Key::Char('(')));
+        buffer.consume(key(Key::Enter));
+        assert_eq!(buffer.collect(), "select * from t where a in (\n  \n)");
+        assert_eq!(buffer.cursor(), 31);
+        for c in "select (b".chars() {
+            buffer.consume(key(Key::Char(c)));
+        }
+        buffer.consume(key(Key::End));
+        buffer.consume(key(Key::Enter));
+        assert_eq!(
+            buffer.collect(),
+            "select * from t where a in (\n  select (b)\n  \n)"
+        );
+    }
+}
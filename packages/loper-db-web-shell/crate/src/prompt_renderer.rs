@@ -0,0 +1,330 @@
+use crate::vt100;
+use std::cmp::Ordering;
+use unicode_width::UnicodeWidthChar;
+
+/// The style of a character in the prompt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// No highlighting
+    Plain,
+    /// A keyword
+    Keyword,
+    /// A numeric or string constant
+    Constant,
+    /// A bracket or quote that matches the one at the cursor
+    Matching,
+}
+
+impl Style {
+    /// Write the escape sequences that switch to the style
+    fn write_to(self, out: &mut String) {
+        out.push_str(vt100::MODES_OFF);
+        match self {
+            Style::Plain => {}
+            Style::Keyword => {
+                out.push_str(vt100::COLOR_FG_GREEN);
+                out.push_str(vt100::MODE_BOLD);
+            }
+            Style::Constant => {
+                out.push_str(vt100::COLOR_FG_MAGENTA);
+                out.push_str(vt100::MODE_BOLD);
+            }
+            Style::Matching => {
+                out.push_str(vt100::MODE_BOLD);
+                out.push_str(vt100::MODE_UNDERLINE);
+            }
+        }
+    }
+}
+
+/// A line of the prompt as it is drawn in the terminal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedLine {
+    /// The prompt of the line
+    pub prompt: String,
+    /// The width of the prompt
+    pub prompt_width: usize,
+    /// The characters of the line with their styles
+    pub cells: Vec<(char, Style)>,
+}
+
+impl RenderedLine {
+    /// Construct an empty line with a prompt
+    pub fn new(prompt: &str, prompt_width: usize) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            prompt_width,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Get the terminal columns of the first n characters including the prompt
+    fn column(&self, n: usize) -> usize {
+        self.prompt_width
+            + self.cells[..n]
+                .iter()
+                .map(|(c, _)| c.width().unwrap_or(0))
+                .sum::<usize>()
+    }
+
+    /// Get the terminal columns of the line including the prompt
+    pub fn width(&self) -> usize {
+        self.column(self.cells.len())
+    }
+}
+
+/// Move the terminal cursor from one row and column to another
+fn move_cursor(out: &mut String, from: &mut (usize, usize), to: (usize, usize)) {
+    match from.0.cmp(&to.0) {
+        Ordering::Less => vt100::cursor_down(out, to.0 - from.0),
+        Ordering::Greater => vt100::cursor_up(out, from.0 - to.0),
+        Ordering::Equal => {}
+    }
+    match from.1.cmp(&to.1) {
+        _ if from.1 != 0 && to.1 == 0 => out.push(vt100::CR),
+        Ordering::Less => vt100::cursor_right(out, to.1 - from.1),
+        Ordering::Greater => vt100::cursor_left(out, from.1 - to.1),
+        Ordering::Equal => {}
+    }
+    *from = to;
+}
+
+/// An incremental renderer for the prompt.
+/// Remembers the lines in the terminal and only writes the characters that changed.
+#[derive(Default)]
+pub struct PromptRenderer {
+    /// The lines in the terminal
+    drawn: Vec<RenderedLine>,
+}
+
+impl PromptRenderer {
+    /// Forget the drawn lines, e.g. after the screen was cleared.
+    /// The next render writes all lines, starting at the current terminal line.
+    pub fn reset(&mut self) {
+        self.drawn.clear();
+    }
+
+    /// Get the prompt width of a drawn line
+    pub fn prompt_width(&self, row: usize) -> Option<usize> {
+        self.drawn.get(row).map(|l| l.prompt_width)
+    }
+
+    /// Render the lines.
+    /// The terminal cursor is expected at the given row and column relative to the begin of the first line.
+    /// Leaves the terminal cursor at the end of the last line.
+    pub fn render(&mut self, out: &mut String, lines: Vec<RenderedLine>, cursor: (usize, usize)) {
+        let mut cursor = cursor;
+        let mut rows = std::cmp::max(self.drawn.len(), 1);
+        for (i, line) in lines.iter().enumerate() {
+            // Find the first changed character of the line
+            let old = self.drawn.get(i);
+            let same = match old {
+                Some(old) if old.prompt == line.prompt => Some(
+                    old.cells
+                        .iter()
+                        .zip(line.cells.iter())
+                        .take_while(|(a, b)| a == b)
+                        .count(),
+                ),
+                _ => None,
+            };
+            if let (Some(old), Some(n)) = (old, same) {
+                if n == old.cells.len() && n == line.cells.len() {
+                    continue;
+                }
+            }
+
+            // Move to the line, start a new terminal line if necessary
+            if i < rows {
+                let col = match same {
+                    Some(n) => line.column(n),
+                    None => 0,
+                };
+                move_cursor(out, &mut cursor, (i, col));
+            } else {
+                let col = cursor.1;
+                move_cursor(out, &mut cursor, (rows - 1, col));
+                for _ in rows..=i {
+                    out.push_str(vt100::CRLF);
+                }
+                rows = i + 1;
+                cursor = (i, 0);
+            }
+
+            // Write the changed characters
+            let begin = match same {
+                Some(n) => n,
+                None => {
+                    out.push_str(&line.prompt);
+                    0
+                }
+            };
+            let mut style = Style::Plain;
+            for (c, s) in line.cells[begin..].iter() {
+                if *s != style {
+                    s.write_to(out);
+                    style = *s;
+                }
+                out.push(*c);
+            }
+            if style != Style::Plain {
+                out.push_str(vt100::MODES_OFF);
+            }
+            cursor.1 = line.width();
+
+            // Clear the remainder of the old line
+            if old.map(|o| o.width() > cursor.1).unwrap_or(false) {
+                out.push_str(vt100::CLEAR_LINE_CURSOR_RIGHT);
+            }
+        }
+
+        // Clear the lines that are no longer needed
+        for i in lines.len()..self.drawn.len() {
+            let col = cursor.1;
+            move_cursor(out, &mut cursor, (i, col));
+            out.push_str(vt100::CLEAR_LINE);
+        }
+
+        // Move to the end of the last line
+        if let Some(last) = lines.last() {
+            move_cursor(out, &mut cursor, (lines.len() - 1, last.width()));
+        }
+        self.drawn = lines;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A minimal terminal screen that understands the escape sequences of the prompt
+    pub(crate) struct Screen {
+        /// The characters and styles of the rows
+        rows: Vec<Vec<(char, String)>>,
+        /// The cursor
+        cursor: (usize, usize),
+        /// The current style
+        style: String,
+    }
+
+    impl Screen {
+        pub(crate) fn new() -> Self {
+            Self {
+                rows: vec![Vec::new()],
+                cursor: (0, 0),
+                style: String::new(),
+            }
+        }
+
+        pub(crate) fn write(&mut self, text: &str) {
+            let chars: Vec<char> = text.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '\r' => self.cursor.1 = 0,
+                    '\n' => self.cursor.0 += 1,
+                    '\u{0008}' => self.cursor.1 = self.cursor.1.saturating_sub(1),
+                    '\x1b' => {
+                        i += 2;
+                        let mut params = String::new();
+                        while !chars[i].is_ascii_alphabetic() {
+                            params.push(chars[i]);
+                            i += 1;
+                        }
+                        let n: usize = params.parse().unwrap_or(1);
+                        let row = &mut self.rows[self.cursor.0];
+                        match chars[i] {
+                            'A' => self.cursor.0 -= n,
+                            'B' => self.cursor.0 += n,
+                            'C' => self.cursor.1 += n,
+                            'D' => self.cursor.1 -= n,
+                            'K' if params == "2" => row.clear(),
+                            'K' => row.truncate(self.cursor.1),
+                            'm' if params.is_empty() => self.style.clear(),
+                            'm' => self.style.push_str(&params),
+                            c => panic!("unexpected escape sequence {}", c),
+                        }
+                    }
+                    c => {
+                        let width = c.width().unwrap_or(0);
+                        let (r, col) = self.cursor;
+                        let row = &mut self.rows[r];
+                        while row.len() < col + width {
+                            row.push((' ', String::new()));
+                        }
+                        row[col] = (c, self.style.clone());
+                        if width > 1 {
+                            row[col + 1] = ('\0', String::new());
+                        }
+                        self.cursor.1 += width;
+                    }
+                }
+                while self.rows.len() <= self.cursor.0 {
+                    self.rows.push(Vec::new());
+                }
+                i += 1;
+            }
+        }
+
+        /// Get the cursor
+        pub(crate) fn cursor(&self) -> (usize, usize) {
+            self.cursor
+        }
+
+        /// Get the characters and styles of the non-empty rows
+        pub(crate) fn rows(&self) -> Vec<Vec<(char, String)>> {
+            let mut rows = self.rows.clone();
+            while rows.last().map(|r| r.is_empty()).unwrap_or(false) {
+                rows.pop();
+            }
+            rows
+        }
+
+        /// Get the text of the non-empty rows
+        pub(crate) fn text(&self) -> Vec<String> {
+            self.rows()
+                .iter()
+                .map(|r| r.iter().map(|(c, _)| *c).filter(|c| *c != '\0').collect())
+                .collect()
+        }
+    }
+
+    fn line(prompt: &str, text: &str) -> RenderedLine {
+        let mut line = RenderedLine::new(prompt, prompt.len());
+        line.cells = text.chars().map(|c| (c, Style::Plain)).collect();
+        line
+    }
+
+    #[test]
+    fn test_render_changed_lines() {
+        let mut renderer = PromptRenderer::default();
+        let mut screen = Screen::new();
+        let mut out = String::new();
+        renderer.render(
+            &mut out,
+            vec![line("> ", "select 1,"), line(". ", "2")],
+            (0, 0),
+        );
+        screen.write(&out);
+        assert_eq!(screen.text(), vec!["> select 1,", ". 2"]);
+        assert_eq!(screen.cursor(), (1, 3));
+
+        // Only the changed character is written
+        out.clear();
+        renderer.render(
+            &mut out,
+            vec![line("> ", "select 3,"), line(". ", "2")],
+            (1, 3),
+        );
+        assert_eq!(out, "\x1b[1A\x1b[6C3,\x1b[1B\x1b[8D");
+        screen.write(&out);
+        assert_eq!(screen.text(), vec!["> select 3,", ". 2"]);
+
+        // Removed lines are cleared
+        out.clear();
+        renderer.render(&mut out, vec![line("> ", "select 3")], (1, 3));
+        screen.write(&out);
+        assert_eq!(screen.text(), vec!["> select 3"]);
+        assert_eq!(screen.cursor(), (0, 10));
+    }
+}
@@ -0,0 +1,478 @@
+use crate::arrow_format::{
+    format_full_value, format_value, DisplayTimeZone, FormatOptions, NumberFormat,
+};
+use crate::comfy::{Cell, Color, ContentArrangement, Row, Table};
+use crate::vt100;
+use arrow::record_batch::RecordBatch;
+use std::collections::HashMap;
+
+/// The maximum number of rows shown of a diff
+pub const MAX_DIFF_ROWS: usize = 100;
+
+/// How a row changed between two results
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RowChange {
+    /// The row is only in the new result
+    Added,
+    /// The row is only in the old result
+    Removed,
+    /// The row is in both results with different values
+    Changed,
+}
+
+/// A row that differs between two results
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowDiff {
+    /// How the row changed
+    pub change: RowChange,
+    /// The formatted values of the old row (if any)
+    pub old: Option<Vec<String>>,
+    /// The formatted values of the new row (if any)
+    pub new: Option<Vec<String>>,
+    /// Which values of a changed row differ, empty for added and removed rows
+    pub changed: Vec<bool>,
+}
+
+/// The differences between two results
+#[derive(Clone, Debug, Default)]
+pub struct ResultDiff {
+    /// The compared columns, these are in both results
+    pub columns: Vec<String>,
+    /// The columns that are only in the old result
+    pub removed_columns: Vec<String>,
+    /// The columns that are only in the new result
+    pub added_columns: Vec<String>,
+    /// The rows that differ
+    pub rows: Vec<RowDiff>,
+    /// The number of equal rows
+    pub unchanged: usize,
+}
+
+/// A value for comparing, None for nulls
+type DiffValue = Option<String>;
+
+/// The rows of a result with the values for comparing and the formatted values for showing
+struct DiffRows {
+    values: Vec<Vec<DiffValue>>,
+    shown: Vec<Vec<String>>,
+}
+
+/// The options for formatting the compared values.
+/// Values are compared in full and independent of the number format and the time zone.
+fn compare_options() -> FormatOptions {
+    FormatOptions {
+        max_depth: usize::MAX,
+        max_length: usize::MAX,
+        max_width: None,
+        numbers: NumberFormat::default(),
+        time_zone: DisplayTimeZone::utc(),
+    }
+}
+
+/// Get the values of columns of the record batches of a result
+fn diff_rows(
+    batches: &[RecordBatch],
+    columns: &[String],
+    options: &FormatOptions,
+) -> Result<DiffRows, String> {
+    let mut rows = DiffRows {
+        values: Vec::new(),
+        shown: Vec::new(),
+    };
+    let compare_options = compare_options();
+    for batch in batches {
+        let schema = batch.schema();
+        let arrays = columns
+            .iter()
+            .map(|name| schema.index_of(name).map(|i| batch.column(i)))
+            .collect::<arrow::error::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        for row in 0..batch.num_rows() {
+            let values = arrays
+                .iter()
+                .map(|array| {
+                    if array.is_null(row) {
+                        Ok(None)
+                    } else {
+                        format_full_value(array, row, &compare_options).map(Some)
+                    }
+                })
+                .collect::<arrow::error::Result<Vec<_>>>()
+                .map_err(|e| e.to_string())?;
+            let shown = arrays
+                .iter()
+                .map(|array| format_value(array, row, options))
+                .collect::<arrow::error::Result<Vec<_>>>()
+                .map_err(|e| e.to_string())?;
+            rows.values.push(values);
+            rows.shown.push(shown);
+        }
+    }
+    Ok(rows)
+}
+
+/// Get the column names of the record batches of a result
+fn column_names(batches: &[RecordBatch]) -> Vec<String> {
+    batches
+        .first()
+        .map(|b| {
+            b.schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Index rows by the values of key columns.
+/// Returns an error if a key is not unique.
+fn index_rows(
+    rows: &DiffRows,
+    keys: &[usize],
+    result: &str,
+) -> Result<HashMap<Vec<DiffValue>, usize>, String> {
+    let mut index = HashMap::new();
+    for (i, row) in rows.values.iter().enumerate() {
+        let key: Vec<DiffValue> = keys.iter().map(|k| row[*k].clone()).collect();
+        if index.insert(key, i).is_some() {
+            let shown: Vec<&str> = keys.iter().map(|k| rows.shown[i][*k].as_str()).collect();
+            return Err(format!(
+                "The key ({}) is not unique in the {} result",
+                shown.join(", "),
+                result
+            ));
+        }
+    }
+    Ok(index)
+}
+
+/// Compare two results.
+/// Rows are matched by the values of key columns or by their position if there are no keys.
+pub fn diff_results(
+    old: &[RecordBatch],
+    new: &[RecordBatch],
+    keys: &[String],
+    options: &FormatOptions,
+) -> Result<ResultDiff, String> {
+    let (old_columns, new_columns) = (column_names(old), column_names(new));
+    let mut diff = ResultDiff {
+        columns: new_columns
+            .iter()
+            .filter(|c| old_columns.contains(c))
+            .cloned()
+            .collect(),
+        removed_columns: old_columns
+            .iter()
+            .filter(|c| !new_columns.contains(c))
+            .cloned()
+            .collect(),
+        added_columns: new_columns
+            .iter()
+            .filter(|c| !old_columns.contains(c))
+            .cloned()
+            .collect(),
+        ..ResultDiff::default()
+    };
+    if diff.columns.is_empty() && !(old_columns.is_empty() || new_columns.is_empty()) {
+        return Err("The results have no columns in common".to_string());
+    }
+    let key_indexes = keys
+        .iter()
+        .map(|key| {
+            diff.columns
+                .iter()
+                .position(|c| c == key)
+                .ok_or_else(|| format!("Unknown key column: {}", key))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let old_rows = diff_rows(old, &diff.columns, options)?;
+    let new_rows = diff_rows(new, &diff.columns, options)?;
+
+    // Match the new rows with the old rows
+    let matches: Vec<Option<usize>> = if key_indexes.is_empty() {
+        (0..new_rows.values.len())
+            .map(|i| {
+                if i < old_rows.values.len() {
+                    Some(i)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    } else {
+        let old_index = index_rows(&old_rows, &key_indexes, "old")?;
+        index_rows(&new_rows, &key_indexes, "new")?;
+        new_rows
+            .values
+            .iter()
+            .map(|row| {
+                let key: Vec<DiffValue> = key_indexes.iter().map(|k| row[*k].clone()).collect();
+                old_index.get(&key).cloned()
+            })
+            .collect()
+    };
+    let mut matched = vec![false; old_rows.values.len()];
+    for (new_row, old_row) in matches.iter().enumerate() {
+        match old_row {
+            Some(old_row) => {
+                matched[*old_row] = true;
+                let (old_values, new_values) =
+                    (&old_rows.values[*old_row], &new_rows.values[new_row]);
+                if old_values == new_values {
+                    diff.unchanged += 1;
+                    continue;
+                }
+                // Changed nulls are named, they would look like empty text otherwise
+                let (mut old, mut new) = (
+                    old_rows.shown[*old_row].clone(),
+                    new_rows.shown[new_row].clone(),
+                );
+                let changed: Vec<bool> = old_values
+                    .iter()
+                    .zip(new_values)
+                    .map(|(o, n)| o != n)
+                    .collect();
+                for (i, changed) in changed.iter().enumerate() {
+                    if *changed && old_values[i].is_none() {
+                        old[i] = "NULL".to_string();
+                    }
+                    if *changed && new_values[i].is_none() {
+                        new[i] = "NULL".to_string();
+                    }
+                }
+                diff.rows.push(RowDiff {
+                    change: RowChange::Changed,
+                    old: Some(old),
+                    new: Some(new),
+                    changed,
+                });
+            }
+            None => diff.rows.push(RowDiff {
+                change: RowChange::Added,
+                old: None,
+                new: Some(new_rows.shown[new_row].clone()),
+                changed: Vec::new(),
+            }),
+        }
+    }
+    for (old_row, matched) in matched.iter().enumerate() {
+        if !matched {
+            diff.rows.push(RowDiff {
+                change: RowChange::Removed,
+                old: Some(old_rows.shown[old_row].clone()),
+                new: None,
+                changed: Vec::new(),
+            });
+        }
+    }
+    Ok(diff)
+}
+
+impl ResultDiff {
+    /// Count the rows with a change
+    pub fn count(&self, change: RowChange) -> usize {
+        self.rows.iter().filter(|r| r.change == change).count()
+    }
+
+    /// Render the differing rows as table, added rows are green, removed rows red and changed rows yellow
+    pub fn render(&self, table_width: u16, presets: &str) -> String {
+        let mut table = Table::new();
+        table.load_preset(presets);
+        table.set_table_width(table_width);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.enforce_styling();
+        let mut header = vec![Cell::new("")];
+        header.extend(self.columns.iter().map(Cell::new));
+        table.set_header(Row::from(header));
+
+        for row in self.rows.iter().take(MAX_DIFF_ROWS) {
+            let (marker, color) = match row.change {
+                RowChange::Added => ("+", Color::Green),
+                RowChange::Removed => ("-", Color::Red),
+                RowChange::Changed => ("~", Color::Yellow),
+            };
+            let mut cells = vec![Cell::new(marker).fg(color)];
+            match (&row.old, &row.new) {
+                // Changed values are shown as old → new
+                (Some(old), Some(new)) => {
+                    cells.extend(old.iter().zip(new).zip(row.changed.iter()).map(
+                        |((old, new), changed)| {
+                            if *changed {
+                                Cell::new(format!("{} → {}", old, new)).fg(color)
+                            } else {
+                                Cell::new(new)
+                            }
+                        },
+                    ))
+                }
+                (Some(values), None) | (None, Some(values)) => {
+                    cells.extend(values.iter().map(|v| Cell::new(v).fg(color)))
+                }
+                (None, None) => {}
+            }
+            table.add_row(Row::from(cells));
+        }
+
+        let mut out = String::new();
+        if !self.rows.is_empty() {
+            out.push_str(&table.to_string());
+            out.push_str(vt100::CRLF);
+        }
+        if self.rows.len() > MAX_DIFF_ROWS {
+            out.push_str(&format!(
+                "Showing the first {} of {} differing rows{}",
+                MAX_DIFF_ROWS,
+                self.rows.len(),
+                vt100::CRLF
+            ));
+        }
+        for (columns, what) in [
+            (&self.removed_columns, "Removed"),
+            (&self.added_columns, "Added"),
+        ] {
+            if !columns.is_empty() {
+                out.push_str(&format!(
+                    "{} columns: {}{}",
+                    what,
+                    columns.join(", "),
+                    vt100::CRLF
+                ));
+            }
+        }
+        out.push_str(&format!(
+            "{} added, {} removed, {} changed, {} unchanged rows",
+            self.count(RowChange::Added),
+            self.count(RowChange::Removed),
+            self.count(RowChange::Changed),
+            self.unchanged
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow_printer::UTF8_BORDERS_NO_HORIZONTAL;
+    use arrow::array::{ArrayRef, Float64Array, Int32Array, StringArray};
+    use std::sync::Arc;
+
+    fn result(ids: Vec<i32>, names: Vec<&str>) -> Vec<RecordBatch> {
+        let ids: ArrayRef = Arc::new(Int32Array::from(ids));
+        let names: ArrayRef = Arc::new(StringArray::from(names));
+        vec![RecordBatch::try_from_iter(vec![("id", ids), ("name", names)]).unwrap()]
+    }
+
+    fn strings(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    #[test]
+    fn test_diff_by_key() {
+        let old = result(vec![1, 2, 3], vec!["a", "b", "c"]);
+        let new = result(vec![3, 1, 4], vec!["c", "x", "d"]);
+        let keys = vec!["id".to_string()];
+        let diff = diff_results(&old, &new, &keys, &FormatOptions::default()).unwrap();
+        assert_eq!(diff.columns, vec!["id", "name"]);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.rows,
+            vec![
+                RowDiff {
+                    change: RowChange::Changed,
+                    old: strings(&["1", "a"]),
+                    new: strings(&["1", "x"]),
+                    changed: vec![false, true],
+                },
+                RowDiff {
+                    change: RowChange::Added,
+                    old: None,
+                    new: strings(&["4", "d"]),
+                    changed: Vec::new(),
+                },
+                RowDiff {
+                    change: RowChange::Removed,
+                    old: strings(&["2", "b"]),
+                    new: None,
+                    changed: Vec::new(),
+                },
+            ]
+        );
+
+        let out = diff.render(40, UTF8_BORDERS_NO_HORIZONTAL);
+        assert!(out.contains("\x1b[93m a → x \x1b[0m"));
+        assert!(out.contains("\x1b[92m 4  \x1b[0m"));
+        assert!(out.contains("\x1b[91m b     \x1b[0m"));
+        assert!(out.ends_with("1 added, 1 removed, 1 changed, 1 unchanged rows"));
+
+        let keys = vec!["name".to_string()];
+        let duplicate = result(vec![1, 2], vec!["a", "a"]);
+        assert_eq!(
+            diff_results(&duplicate, &new, &keys, &FormatOptions::default()).unwrap_err(),
+            "The key (a) is not unique in the old result"
+        );
+        let keys = vec!["missing".to_string()];
+        assert!(diff_results(&old, &new, &keys, &FormatOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_diff_by_position() {
+        let old = result(vec![1, 2], vec!["a", "b"]);
+        let ids: ArrayRef = Arc::new(Int32Array::from(vec![1, 5, 6]));
+        let new = vec![RecordBatch::try_from_iter(vec![("id", ids)]).unwrap()];
+        let diff = diff_results(&old, &new, &[], &FormatOptions::default()).unwrap();
+        assert_eq!(diff.columns, vec!["id"]);
+        assert_eq!(diff.removed_columns, vec!["name"]);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.count(RowChange::Changed), 1);
+        assert_eq!(diff.count(RowChange::Added), 1);
+        assert!(diff
+            .render(40, UTF8_BORDERS_NO_HORIZONTAL)
+            .ends_with("Removed columns: name\r\n1 added, 0 removed, 1 changed, 1 unchanged rows"));
+
+        // Equal results have no table
+        let diff = diff_results(&old, &old, &[], &FormatOptions::default()).unwrap();
+        assert_eq!(
+            diff.render(40, UTF8_BORDERS_NO_HORIZONTAL),
+            "0 added, 0 removed, 0 changed, 2 unchanged rows"
+        );
+    }
+
+    #[test]
+    fn test_diff_nulls_and_precision() {
+        let ids: ArrayRef = Arc::new(Int32Array::from(vec![1, 2]));
+        let names: ArrayRef = Arc::new(StringArray::from(vec![Some(""), Some("a")]));
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 2.0]));
+        let old = vec![RecordBatch::try_from_iter(vec![
+            ("id", ids.clone()),
+            ("name", names),
+            ("value", values),
+        ])
+        .unwrap()];
+        let names: ArrayRef = Arc::new(StringArray::from(vec![None, Some("a")]));
+        let values: ArrayRef = Arc::new(Float64Array::from(vec![1.0, 2.0001]));
+        let new =
+            vec![
+                RecordBatch::try_from_iter(vec![("id", ids), ("name", names), ("value", values)])
+                    .unwrap(),
+            ];
+
+        // NULL differs from empty text and floats are compared beyond the shown precision
+        let mut options = FormatOptions::default();
+        options.numbers.precision = Some(2);
+        let keys = vec!["id".to_string()];
+        let diff = diff_results(&old, &new, &keys, &options).unwrap();
+        assert_eq!(diff.unchanged, 0);
+        assert_eq!(diff.rows[0].new, strings(&["1", "NULL", "1.00"]));
+        assert_eq!(diff.rows[0].changed, vec![false, true, false]);
+        assert_eq!(diff.rows[1].old, strings(&["2", "a", "2.00"]));
+        assert_eq!(diff.rows[1].changed, vec![false, false, true]);
+
+        // NULL and empty text are different keys
+        let keys = vec!["name".to_string()];
+        let diff = diff_results(&old, &new, &keys, &options).unwrap();
+        assert_eq!(diff.count(RowChange::Added), 1);
+        assert_eq!(diff.count(RowChange::Removed), 1);
+    }
+}
@@ -0,0 +1,755 @@
+use crate::arrow_format::{format_full_value, FormatOptions};
+use crate::arrow_printer::get_column_alignment;
+use crate::comfy::{Cell, CellAlignment, ColumnConstraint, ContentArrangement, Row, Table};
+use crate::key_event::{Key, KeyEvent, MouseEvent, MOUSE_WHEEL_DOWN, MOUSE_WHEEL_UP};
+use crate::vt100;
+use arrow::error::Result;
+use arrow::record_batch::RecordBatch;
+use std::fmt::Write;
+use unicode_width::UnicodeWidthChar;
+
+/// The maximum content width of a column, longer values are truncated
+const MAX_COLUMN_WIDTH: u16 = 32;
+/// The number of columns that stay visible when scrolling horizontally
+const FROZEN_COLUMNS: usize = 1;
+/// The terminal rows above the grid rows, the header and a separator
+const HEADER_ROWS: usize = 2;
+/// The number of rows that are scrolled with the mouse wheel
+const WHEEL_ROWS: usize = 3;
+
+/// What the shell should do after an input in the grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridAction {
+    /// Nothing changed
+    None,
+    /// Draw the grid again
+    Redraw,
+    /// Leave the grid
+    Quit,
+}
+
+/// A column of the grid
+struct GridColumn {
+    /// The column name
+    name: String,
+    /// The name of the data type
+    data_type: String,
+    /// The content width
+    width: usize,
+    /// The alignment of the values
+    alignment: CellAlignment,
+}
+
+/// A visible column on the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ColumnSlot {
+    /// The column index
+    column: usize,
+    /// The first terminal column
+    x: usize,
+    /// The visible content width
+    width: usize,
+}
+
+/// A popup that shows the full value of a cell
+struct CellDetail {
+    /// The title of the popup
+    title: String,
+    /// The wrapped lines of the value
+    lines: Vec<String>,
+    /// The first visible line
+    top: usize,
+}
+
+/// A full-screen grid to explore a result cell by cell.
+/// The header and the first column stay visible when scrolling.
+pub struct ResultGrid {
+    /// The columns
+    columns: Vec<GridColumn>,
+    /// The values of the rows
+    rows: Vec<Vec<String>>,
+    /// The row of the cell cursor
+    row: usize,
+    /// The column of the cell cursor
+    column: usize,
+    /// The first visible row
+    top: usize,
+    /// The first visible column after the frozen columns
+    left: usize,
+    /// The terminal rows
+    term_rows: usize,
+    /// The terminal columns
+    term_cols: usize,
+    /// The open cell detail (if any)
+    detail: Option<CellDetail>,
+}
+
+/// Get the terminal columns of a text
+fn text_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// Append a character, control characters are escaped visibly so they cannot reach the terminal
+fn push_visible(out: &mut String, c: char) {
+    match c {
+        '\t' => out.push(' '),
+        '\r' => out.push_str("\\r"),
+        c if c.is_control() => write!(out, "\\x{:02x}", c as u32).unwrap(),
+        c => out.push(c),
+    }
+}
+
+/// Get the single line text of a value
+fn display_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\r' => {}
+            '\n' => text.push('↵'),
+            c => push_visible(&mut text, c),
+        }
+    }
+    text
+}
+
+/// Truncate or pad a text to a width
+fn fit(text: &str, width: usize, alignment: CellAlignment) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    if text_width(text) > width {
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w + 1 > width {
+                break;
+            }
+            fitted.push(c);
+            used += w;
+        }
+        if width > 0 {
+            fitted.push('…');
+            used += 1;
+        }
+        return format!("{}{}", fitted, " ".repeat(width - used));
+    }
+    let padding = width - text_width(text);
+    let (before, after) = match alignment {
+        CellAlignment::Left => (0, padding),
+        CellAlignment::Right => (padding, 0),
+        CellAlignment::Center => (padding / 2, padding - padding / 2),
+    };
+    format!("{}{}{}", " ".repeat(before), text, " ".repeat(after))
+}
+
+/// Wrap the lines of a text at a width
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.split('\n') {
+        let mut current = String::new();
+        let mut used = 0;
+        let mut visible = String::with_capacity(line.len());
+        for c in line.trim_end_matches('\r').chars() {
+            push_visible(&mut visible, c);
+        }
+        for c in visible.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+                used = 0;
+            }
+            current.push(c);
+            used += w;
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+/// Pretty print a value if it is a JSON object or array
+fn pretty_json(value: &str) -> Option<String> {
+    let trimmed = value.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    let json: serde_json::Value = serde_json::from_str(value).ok()?;
+    serde_json::to_string_pretty(&json).ok()
+}
+
+impl ResultGrid {
+    /// Construct a grid for the record batches of a result
+    pub fn new(
+        batches: &[RecordBatch],
+        options: &FormatOptions,
+        term_rows: usize,
+        term_cols: usize,
+    ) -> Result<Self> {
+        let mut rows = Vec::new();
+        for batch in batches {
+            for row in 0..batch.num_rows() {
+                let values = batch
+                    .columns()
+                    .iter()
+                    // The grid shows line breaks itself and the detail needs the full value
+                    .map(|column| format_full_value(column, row, options))
+                    .collect::<Result<Vec<_>>>()?;
+                rows.push(values);
+            }
+        }
+
+        // Let the table layout determine the column widths
+        let mut table = Table::new();
+        table.set_content_arrangement(ContentArrangement::Disabled);
+        let mut columns = Vec::new();
+        if let Some(first) = batches.first() {
+            let schema = first.schema();
+            table.set_header(Row::from(
+                schema
+                    .fields()
+                    .iter()
+                    .map(|f| Cell::new(display_text(f.name())))
+                    .collect::<Vec<_>>(),
+            ));
+            for (field, column) in schema.fields().iter().zip(first.columns()) {
+                columns.push(GridColumn {
+                    name: display_text(field.name()),
+                    data_type: field.data_type().to_string(),
+                    width: 0,
+                    alignment: get_column_alignment(column),
+                });
+            }
+        }
+        for values in rows.iter() {
+            table.add_row(Row::from(
+                values
+                    .iter()
+                    .map(|v| Cell::new(display_text(v)))
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        for column in table.column_iter_mut() {
+            let (left, right) = column.padding;
+            column.set_constraint(ColumnConstraint::MaxWidth(MAX_COLUMN_WIDTH + left + right));
+        }
+        for (column, width) in columns.iter_mut().zip(table.column_content_widths()) {
+            column.width = width as usize;
+        }
+
+        Ok(Self {
+            left: std::cmp::min(FROZEN_COLUMNS, columns.len()),
+            columns,
+            rows,
+            row: 0,
+            column: 0,
+            top: 0,
+            term_rows,
+            term_cols,
+            detail: None,
+        })
+    }
+
+    /// Get the number of visible grid rows
+    fn page_rows(&self) -> usize {
+        std::cmp::max(self.term_rows.saturating_sub(HEADER_ROWS + 1), 1)
+    }
+
+    /// Get the visible columns, the frozen columns first
+    fn slots(&self) -> Vec<ColumnSlot> {
+        let frozen = std::cmp::min(FROZEN_COLUMNS, self.columns.len());
+        let mut slots = Vec::new();
+        let mut x = 0;
+        for column in (0..frozen).chain(self.left..self.columns.len()) {
+            // Every column has a padding on both sides and a separator
+            let available = self.term_cols.saturating_sub(x + 3);
+            if available == 0 {
+                break;
+            }
+            let width = std::cmp::min(self.columns[column].width, available);
+            slots.push(ColumnSlot { column, x, width });
+            x += width + 3;
+        }
+        slots
+    }
+
+    /// Scroll the cell cursor into view
+    fn scroll_to_cursor(&mut self) {
+        let page = self.page_rows();
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + page {
+            self.top = self.row + 1 - page;
+        }
+        if self.column < FROZEN_COLUMNS {
+            return;
+        }
+        if self.column < self.left {
+            self.left = self.column;
+        }
+        let fully_visible = |grid: &Self| {
+            grid.slots()
+                .iter()
+                .any(|s| s.column == grid.column && s.width == grid.columns[grid.column].width)
+        };
+        while self.left < self.column && !fully_visible(self) {
+            self.left += 1;
+        }
+    }
+
+    /// Move the cell cursor
+    fn move_cursor(&mut self, rows: isize, columns: isize) {
+        let clamp = |value: usize, delta: isize, len: usize| {
+            let moved = std::cmp::max(value as isize + delta, 0) as usize;
+            std::cmp::min(moved, len.saturating_sub(1))
+        };
+        self.row = clamp(self.row, rows, self.rows.len());
+        self.column = clamp(self.column, columns, self.columns.len());
+        self.scroll_to_cursor();
+    }
+
+    /// Open the detail popup for the cell under the cursor
+    fn open_detail(&mut self) {
+        let value = match self.rows.get(self.row).and_then(|r| r.get(self.column)) {
+            Some(value) => value,
+            None => return,
+        };
+        let text = pretty_json(value).unwrap_or_else(|| value.clone());
+        let width = std::cmp::max(self.term_cols.saturating_sub(8), 1);
+        self.detail = Some(CellDetail {
+            title: format!("{} (row {})", self.columns[self.column].name, self.row + 1),
+            lines: wrap(&text, width),
+            top: 0,
+        });
+    }
+
+    /// Get the size of the detail popup without the border
+    fn detail_size(&self, detail: &CellDetail) -> (usize, usize) {
+        let widest = detail
+            .lines
+            .iter()
+            .map(|l| text_width(l))
+            .chain(std::iter::once(text_width(&detail.title) + 2))
+            .max()
+            .unwrap_or(0);
+        let width = std::cmp::min(widest, self.term_cols.saturating_sub(8));
+        let height = std::cmp::min(detail.lines.len(), self.term_rows.saturating_sub(6));
+        (std::cmp::max(height, 1), std::cmp::max(width, 1))
+    }
+
+    /// Scroll the detail popup
+    fn scroll_detail(&mut self, delta: isize) {
+        let page = match self.detail {
+            Some(ref detail) => self.detail_size(detail).0,
+            None => return,
+        };
+        if let Some(ref mut detail) = self.detail {
+            let max_top = detail.lines.len().saturating_sub(page);
+            let top = std::cmp::max(detail.top as isize + delta, 0) as usize;
+            detail.top = std::cmp::min(top, max_top);
+        }
+    }
+
+    /// Process a key in the detail popup
+    fn on_detail_key(&mut self, event: &KeyEvent) -> GridAction {
+        let page = self.page_rows() as isize;
+        match event.key {
+            Key::Escape | Key::Enter | Key::Char('q') => self.detail = None,
+            Key::ArrowDown | Key::Char('j') => self.scroll_detail(1),
+            Key::ArrowUp | Key::Char('k') => self.scroll_detail(-1),
+            Key::PageDown | Key::Char(' ') => self.scroll_detail(page),
+            Key::PageUp => self.scroll_detail(-page),
+            _ => return GridAction::None,
+        }
+        GridAction::Redraw
+    }
+
+    /// Process a key
+    pub fn on_key(&mut self, event: &KeyEvent) -> GridAction {
+        if self.detail.is_some() {
+            return self.on_detail_key(event);
+        }
+        let page = self.page_rows() as isize;
+        let rows = self.rows.len() as isize;
+        let columns = self.columns.len() as isize;
+        match event.key {
+            Key::Char('q') | Key::Escape => return GridAction::Quit,
+            Key::Char('c') if event.ctrl => return GridAction::Quit,
+            Key::Char(_) if event.ctrl || event.alt => return GridAction::None,
+            Key::ArrowDown | Key::Char('j') => self.move_cursor(1, 0),
+            Key::ArrowUp | Key::Char('k') => self.move_cursor(-1, 0),
+            Key::ArrowRight | Key::Char('l') => self.move_cursor(0, 1),
+            Key::ArrowLeft | Key::Char('h') => self.move_cursor(0, -1),
+            Key::Tab if event.shift => self.move_cursor(0, -1),
+            Key::Tab => self.move_cursor(0, 1),
+            Key::PageDown | Key::Char(' ') => self.move_cursor(page, 0),
+            Key::PageUp => self.move_cursor(-page, 0),
+            Key::Home => self.move_cursor(0, -columns),
+            Key::End => self.move_cursor(0, columns),
+            Key::Char('g') => self.move_cursor(-rows, 0),
+            Key::Char('G') => self.move_cursor(rows, 0),
+            Key::Enter => self.open_detail(),
+            _ => return GridAction::None,
+        }
+        GridAction::Redraw
+    }
+
+    /// Process a mouse event.
+    /// A click selects a cell, a click on the selected cell opens the detail.
+    pub fn on_mouse(&mut self, event: &MouseEvent) -> GridAction {
+        if self.detail.is_some() {
+            match event.button {
+                MOUSE_WHEEL_UP => self.scroll_detail(-(WHEEL_ROWS as isize)),
+                MOUSE_WHEEL_DOWN => self.scroll_detail(WHEEL_ROWS as isize),
+                0 if event.pressed => self.detail = None,
+                _ => return GridAction::None,
+            }
+            return GridAction::Redraw;
+        }
+        match event.button {
+            MOUSE_WHEEL_UP => self.top = self.top.saturating_sub(WHEEL_ROWS),
+            MOUSE_WHEEL_DOWN => {
+                let max_top = self.rows.len().saturating_sub(self.page_rows());
+                self.top = std::cmp::min(self.top + WHEEL_ROWS, max_top);
+            }
+            0 if event.pressed => {
+                let slot = match self
+                    .slots()
+                    .into_iter()
+                    .find(|s| event.col >= s.x && event.col < s.x + s.width + 3)
+                {
+                    Some(slot) => slot,
+                    None => return GridAction::None,
+                };
+                if event.row < HEADER_ROWS {
+                    self.column = slot.column;
+                } else if event.row < HEADER_ROWS + self.page_rows() {
+                    let row = self.top + event.row - HEADER_ROWS;
+                    if row >= self.rows.len() {
+                        return GridAction::None;
+                    }
+                    if (row, slot.column) == (self.row, self.column) {
+                        self.open_detail();
+                    }
+                    self.row = row;
+                    self.column = slot.column;
+                } else {
+                    return GridAction::None;
+                }
+                self.scroll_to_cursor();
+            }
+            _ => return GridAction::None,
+        }
+        GridAction::Redraw
+    }
+
+    /// Get the separator after a column
+    fn separator(&self, column: usize, line: bool) -> char {
+        match (column + 1 == FROZEN_COLUMNS, line) {
+            (true, false) => '┃',
+            (true, true) => '╂',
+            (false, false) => '│',
+            (false, true) => '┼',
+        }
+    }
+
+    /// Render the detail popup over the grid
+    fn render_detail(&self, out: &mut String, detail: &CellDetail) {
+        let (height, width) = self.detail_size(detail);
+        let y = self.term_rows.saturating_sub(height + 2) / 2;
+        let x = self.term_cols.saturating_sub(width + 4) / 2;
+        let title = fit(&detail.title, width.saturating_sub(1), CellAlignment::Left);
+        let title = title.trim_end();
+        vt100::cursor_position(out, y, x);
+        write!(
+            out,
+            "┌─ {bold}{title}{normal} {line}┐",
+            bold = vt100::MODE_BOLD,
+            title = title,
+            normal = vt100::MODES_OFF,
+            line = "─".repeat((width + 1).saturating_sub(text_width(title) + 2))
+        )
+        .unwrap();
+        for i in 0..height {
+            vt100::cursor_position(out, y + 1 + i, x);
+            let line = detail.lines.get(detail.top + i).map(|l| l.as_str());
+            write!(
+                out,
+                "│ {} │",
+                fit(line.unwrap_or(""), width, CellAlignment::Left)
+            )
+            .unwrap();
+        }
+        vt100::cursor_position(out, y + height + 1, x);
+        let position = if detail.lines.len() > height {
+            format!(
+                " {}-{}/{} ",
+                detail.top + 1,
+                detail.top + height,
+                detail.lines.len()
+            )
+        } else {
+            String::new()
+        };
+        write!(
+            out,
+            "└{}{}─┘",
+            "─".repeat((width + 1).saturating_sub(text_width(&position))),
+            position
+        )
+        .unwrap();
+    }
+
+    /// Get the text of the status line
+    fn status(&self) -> String {
+        if self.rows.is_empty() || self.columns.is_empty() {
+            return "No rows (q to quit)".to_string();
+        }
+        let column = &self.columns[self.column];
+        format!(
+            "row {}/{}, column {}/{}: {} {} (enter for details, q to quit)",
+            self.row + 1,
+            self.rows.len(),
+            self.column + 1,
+            self.columns.len(),
+            column.name,
+            column.data_type
+        )
+    }
+
+    /// Render the grid, the caller switches to the alternate screen first
+    pub fn render(&self, out: &mut String) {
+        let slots = self.slots();
+
+        // The header
+        vt100::cursor_position(out, 0, 0);
+        for slot in slots.iter() {
+            let column = &self.columns[slot.column];
+            write!(
+                out,
+                " {bold}{name}{normal} {separator}",
+                bold = vt100::MODE_BOLD,
+                name = fit(&column.name, slot.width, CellAlignment::Left),
+                normal = vt100::MODES_OFF,
+                separator = self.separator(slot.column, false)
+            )
+            .unwrap();
+        }
+        out.push_str(vt100::CLEAR_LINE_CURSOR_RIGHT);
+        vt100::cursor_position(out, 1, 0);
+        for slot in slots.iter() {
+            out.push_str(&"─".repeat(slot.width + 2));
+            out.push(self.separator(slot.column, true));
+        }
+        out.push_str(vt100::CLEAR_LINE_CURSOR_RIGHT);
+
+        // The rows
+        for i in 0..self.page_rows() {
+            vt100::cursor_position(out, HEADER_ROWS + i, 0);
+            let row = self.top + i;
+            match self.rows.get(row) {
+                Some(values) => {
+                    for slot in slots.iter() {
+                        let text = fit(
+                            &display_text(&values[slot.column]),
+                            slot.width,
+                            self.columns[slot.column].alignment,
+                        );
+                        if (row, slot.column) == (self.row, self.column) {
+                            write!(out, "{} {} {}", vt100::MODE_REVERSE, text, vt100::MODES_OFF)
+                                .unwrap();
+                        } else {
+                            write!(out, " {} ", text).unwrap();
+                        }
+                        out.push(self.separator(slot.column, false));
+                    }
+                }
+                None => out.push('~'),
+            }
+            out.push_str(vt100::CLEAR_LINE_CURSOR_RIGHT);
+        }
+
+        // The status line
+        vt100::cursor_position(out, self.term_rows.saturating_sub(1), 0);
+        write!(
+            out,
+            "{reverse}{status}{normal}{clear}",
+            reverse = vt100::MODE_REVERSE,
+            status = self
+                .status()
+                .chars()
+                .take(self.term_cols)
+                .collect::<String>(),
+            normal = vt100::MODES_OFF,
+            clear = vt100::CLEAR_LINE_CURSOR_RIGHT
+        )
+        .unwrap();
+
+        if let Some(ref detail) = self.detail {
+            self.render_detail(out, detail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    fn key(k: Key) -> KeyEvent {
+        KeyEvent {
+            key: k,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    fn click(row: usize, col: usize) -> MouseEvent {
+        MouseEvent {
+            button: 0,
+            row,
+            col,
+            pressed: true,
+        }
+    }
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("payload", DataType::Utf8, false),
+            Field::new("note", DataType::Utf8, false),
+        ]));
+        let ids: Vec<i32> = (1..=20).collect();
+        let names: Vec<String> = ids.iter().map(|i| format!("name {}", i)).collect();
+        let payloads: Vec<String> = ids
+            .iter()
+            .map(|i| {
+                format!(
+                    "{{\"id\":{},\"tags\":[\"a\",\"b\"],\"text\":\"{}\"}}",
+                    i,
+                    "x".repeat(40)
+                )
+            })
+            .collect();
+        let notes: Vec<&str> = ids.iter().map(|_| "line 1\nline 2").collect();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(StringArray::from(
+                    names.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+                )),
+                Arc::new(StringArray::from(
+                    payloads.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+                )),
+                Arc::new(StringArray::from(notes)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_grid_navigation() -> Result<()> {
+        let mut grid = ResultGrid::new(&[batch()], &FormatOptions::default(), 10, 40)?;
+        let widths: Vec<usize> = grid.columns.iter().map(|c| c.width).collect();
+        assert_eq!(widths, vec![2, 7, 32, 13]);
+        assert_eq!(
+            grid.slots().iter().map(|s| s.column).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        // The first column stays visible
+        grid.on_key(&key(Key::End));
+        assert_eq!((grid.column, grid.left), (3, 3));
+        assert_eq!(
+            grid.slots().iter().map(|s| s.column).collect::<Vec<_>>(),
+            vec![0, 3]
+        );
+        grid.on_key(&key(Key::Home));
+        assert_eq!((grid.column, grid.left), (0, 3));
+        grid.on_key(&key(Key::ArrowRight));
+        assert_eq!((grid.column, grid.left), (1, 1));
+
+        // Rows scroll below the header
+        grid.on_key(&key(Key::PageDown));
+        assert_eq!((grid.row, grid.top), (7, 1));
+        grid.on_key(&key(Key::Char('G')));
+        assert_eq!((grid.row, grid.top), (19, 13));
+        grid.on_mouse(&MouseEvent {
+            button: MOUSE_WHEEL_UP,
+            row: 3,
+            col: 3,
+            pressed: true,
+        });
+        assert_eq!(grid.top, 10);
+        grid.on_mouse(&click(2, 1));
+        assert_eq!((grid.row, grid.column), (10, 0));
+        assert_eq!(grid.on_key(&key(Key::Char('q'))), GridAction::Quit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cell_detail() -> Result<()> {
+        let mut grid = ResultGrid::new(&[batch()], &FormatOptions::default(), 20, 40)?;
+        // A second click on the selected cell opens the detail
+        grid.on_mouse(&click(3, 17));
+        assert_eq!((grid.row, grid.column), (1, 2));
+        assert!(grid.detail.is_none());
+        grid.on_mouse(&click(3, 17));
+        let detail = grid.detail.as_ref().unwrap();
+        assert_eq!(detail.title, "payload (row 2)");
+        assert_eq!(
+            detail.lines,
+            vec![
+                "{",
+                "  \"id\": 2,",
+                "  \"tags\": [",
+                "    \"a\",",
+                "    \"b\"",
+                "  ],",
+                "  \"text\": \"xxxxxxxxxxxxxxxxxxxxx",
+                "xxxxxxxxxxxxxxxxxxx\"",
+                "}",
+            ]
+        );
+        let mut out = String::new();
+        grid.render(&mut out);
+        assert!(out.contains(&format!(
+            "┌─ {}payload (row 2){}",
+            vt100::MODE_BOLD,
+            vt100::MODES_OFF
+        )));
+        assert_eq!(grid.on_key(&key(Key::Escape)), GridAction::Redraw);
+        assert!(grid.detail.is_none());
+
+        // Values are shown on a single line
+        assert_eq!(
+            fit(&display_text("line 1\nline 2"), 8, CellAlignment::Left),
+            "line 1↵…"
+        );
+        assert_eq!(display_text("a\x1b[2Jb\r\tc"), "a\\x1b[2Jb c");
+        assert_eq!(
+            wrap("a\x1bb\r\n\x07\rc", 4),
+            vec!["a\\x1", "bb", "\\x07", "\\rc"]
+        );
+        assert_eq!(fit("42", 5, CellAlignment::Right), "   42");
+        assert_eq!(
+            MouseEvent::parse_reports("\x1b[<0;12;5M\x1b[<65;1;1m"),
+            vec![
+                MouseEvent {
+                    button: 0,
+                    row: 4,
+                    col: 11,
+                    pressed: true,
+                },
+                MouseEvent {
+                    button: MOUSE_WHEEL_DOWN,
+                    row: 0,
+                    col: 0,
+                    pressed: false,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
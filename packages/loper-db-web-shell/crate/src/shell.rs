@@ -1,33 +1,60 @@
-use crate::arrow_printer::{pretty_format_batches, UTF8_BORDERS_NO_HORIZONTAL};
+use crate::arrow_format::{format_full_value, DisplayTimeZone, FormatOptions, NumberFormat};
+use crate::arrow_printer::{pretty_format_batches_with_options, UTF8_BORDERS_NO_HORIZONTAL};
+use crate::chart::{self, ChartKind};
+use crate::comfy::{Cell, CellAlignment, ContentArrangement, Row, Table};
+use crate::completion::{self, Catalog};
+use crate::error::QueryError;
 use crate::loper::{
-    LoperServiceClient, LoperServiceConnection, PACKAGE_NAME, PACKAGE_VERSION, JsLoperServiceClient,
+    tokens, LoperServiceClient, LoperServiceConnection, PACKAGE_NAME, PACKAGE_VERSION, JsLoperServiceClient,
 };
-use crate::key_event::{Key, KeyEvent};
-use crate::prompt_buffer::PromptBuffer;
+use crate::key_event::{Key, KeyEvent, MouseEvent};
+use crate::pager::{Pager, PagerAction};
+use crate::prompt_buffer::{self, PromptBuffer};
+use crate::result_diff::diff_results;
+use crate::result_grid::{GridAction, ResultGrid};
 use crate::shell_options::ShellOptions;
-use crate::shell_runtime::{ShellRuntime};
-use crate::utils::{now, pretty_elapsed};
+use crate::shell_runtime::{ConnectionProfile, HistoryEntry, ShellRuntime};
+use crate::summary::{self, strip_summarize};
+use crate::terminal_search::{self, FindOptions};
+use crate::transcript::{self, TranscriptFormat};
+use crate::utils::{
+    local_time_zone, locale_separators, now, now_timestamp, pretty_bytes, pretty_elapsed,
+    pretty_timestamp, sleep, zone_offset,
+};
 use crate::vt100;
+use crate::xterm::addons::search::{SearchAddon, SearchOptions};
+use crate::xterm::addons::serialize::SerializeAddon;
 use crate::xterm::Terminal;
 use arrow::array::Array;
 use arrow::array::StringArray;
 use arrow::datatypes::{DataType};
+use arrow::record_batch::RecordBatch;
 use chrono::Duration;
-use log::warn;
+use log::{info, warn};
 use scopeguard::defer;
 use std::cell::RefCell;
-use std::collections::{VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use std::sync::RwLock;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::spawn_local;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
 
 thread_local! {
     static SHELL: RefCell<Shell> = RefCell::new(Shell::default());
 }
 
 const HISTORY_LENGTH: usize = 1000;
+/// The number of reconnect attempts before the shell goes offline
+const RECONNECT_ATTEMPTS: u32 = 8;
+/// The delay before the first reconnect attempt in milliseconds
+const RECONNECT_DELAY_MIN: i32 = 500;
+/// The maximum delay between reconnect attempts in milliseconds
+const RECONNECT_DELAY_MAX: i32 = 30_000;
+/// The number of round trips of a ping
+const PING_COUNT: usize = 3;
+/// The table width for the pager, wide enough to not wrap the cells
+const PAGER_TABLE_WIDTH: u16 = 4096;
 
 /// A shell input context
 #[wasm_bindgen]
@@ -35,12 +62,117 @@ pub enum ShellInputContext {
     FileInput = 0,
 }
 
+/// A pending tab completion.
+/// Repeated tabs cycle through the candidates.
+/// The positions are offsets into the collected input, ignoring artificial line wraps.
+struct CompletionState {
+    /// The begin of the completed word
+    begin: usize,
+    /// The end of the inserted candidate
+    end: usize,
+    /// The candidates
+    candidates: Vec<String>,
+    /// The index of the inserted candidate (if any)
+    index: Option<usize>,
+    /// The input clock after the completion
+    input_clock: u64,
+}
+
+/// A pending reverse incremental history search
+struct HistorySearch {
+    /// The search text
+    query: String,
+    /// The index of the matching history entry (if any)
+    match_idx: Option<usize>,
+    /// The input before the search started
+    original_input: String,
+}
+
+/// A pending search in the terminal output
+struct TerminalFind {
+    /// The search text
+    query: String,
+    /// The search options
+    options: FindOptions,
+    /// The first buffer row of the prompt, the prompt itself is not searched
+    end_row: Option<usize>,
+}
+
+impl TerminalFind {
+    /// Get the prompt with the options and the match count
+    fn prompt(&self, index: Option<usize>, count: usize) -> String {
+        let mut flags = String::new();
+        if self.options.case_sensitive {
+            flags.push_str(" case");
+        }
+        if self.options.regex {
+            flags.push_str(" regex");
+        }
+        match (count, index) {
+            (0, _) if !self.query.is_empty() => format!("(failed find{})'{}': ", flags, self.query),
+            (0, _) => format!("(find{})'': ", flags),
+            (n, Some(i)) => format!("(find{} {}/{})'{}': ", flags, i + 1, n, self.query),
+            (n, None) => format!("(find{} {})'{}': ", flags, n, self.query),
+        }
+    }
+}
+
+/// The state of the service connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConnectionState {
+    /// The service is connected
+    Connected,
+    /// The connection was lost and the shell is reconnecting
+    Reconnecting,
+    /// The shell gave up reconnecting
+    Offline,
+}
+
+impl ConnectionState {
+    /// Get the label and color of the prompt status (if any)
+    fn status(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            ConnectionState::Connected => None,
+            ConnectionState::Reconnecting => Some(("reconnecting", vt100::COLOR_FG_YELLOW)),
+            ConnectionState::Offline => Some(("offline", vt100::COLOR_FG_RED)),
+        }
+    }
+}
+
+/// When results are shown in the pager
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PagerMode {
+    /// Never
+    Off,
+    /// Always
+    On,
+    /// If the table does not fit into the terminal
+    Auto,
+}
+
+/// What the query timer shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TimerMode {
+    /// Nothing
+    Off,
+    /// The elapsed time
+    On,
+    /// The elapsed time split into server, transfer, decode and render
+    Detail,
+}
+
 /// Shell settings
 struct ShellSettings {
     /// Enable query output
     output: bool,
     /// Enable query timer
-    timer: bool,
+    timer: TimerMode,
+    /// Run the complete statements of pasted scripts
+    paste_run: bool,
+    /// Show results in the pager
+    pager: PagerMode,
+    /// The options for formatting values
+    format: FormatOptions,
     /// Is WebGL enabled?
     webgl: bool,
 }
@@ -49,7 +181,10 @@ impl ShellSettings {
     fn default() -> Self {
         Self {
             output: true,
-            timer: true,
+            timer: TimerMode::On,
+            paste_run: false,
+            pager: PagerMode::Off,
+            format: FormatOptions::default(),
             webgl: false,
         }
     }
@@ -73,15 +208,51 @@ pub struct Shell {
     /// The input clock
     input_clock: u64,
     /// This history buffer
-    history: VecDeque<String>,
+    history: VecDeque<HistoryEntry>,
     /// This history buffer
     history_cursor: usize,
+    /// The pending history search (if any)
+    history_search: Option<HistorySearch>,
+    /// The addon that searches the terminal output
+    search_addon: Option<SearchAddon>,
+    /// The pending search in the terminal output (if any)
+    terminal_find: Option<TerminalFind>,
+    /// The addon that serializes the terminal buffer
+    serialize_addon: Option<SerializeAddon>,
+    /// The session log without escape sequences while recording (if any)
+    recording: Option<String>,
+    /// The pager that shows a result in the alternate screen (if any)
+    pager: Option<Pager>,
+    /// Are pasted statements running? Their results are printed without the pager.
+    running_script: bool,
+    /// The record batches of the last result
+    last_result: Vec<RecordBatch>,
+    /// The record batches of the result before the last one
+    previous_result: Vec<RecordBatch>,
+    /// The results that were saved for comparing them
+    saved_results: BTreeMap<String, Vec<RecordBatch>>,
+    /// The grid that explores the last result in the alternate screen (if any)
+    grid: Option<ResultGrid>,
     /// The database path
     service_url: String,
+    /// The metadata that is sent with every request
+    service_metadata: BTreeMap<String, String>,
+    /// The session parameters that are sent with every query, also after reconnects
+    session_params: BTreeMap<String, String>,
+    /// The state of the connection
+    connection_state: ConnectionState,
+    /// Incremented with every connection change to stop outdated reconnects
+    connection_epoch: u64,
+    /// The connection profiles
+    profiles: Vec<ConnectionProfile>,
     /// The client (if any)
     service_client: Option<Arc<RwLock<LoperServiceClient>>>,
     /// The connection (if any)
     service_conn: Option<Arc<RwLock<LoperServiceConnection>>>,
+    /// The cached catalog of the service
+    catalog: Catalog,
+    /// The pending tab completion (if any)
+    completion: Option<CompletionState>,
 }
 
 impl Shell {
@@ -97,9 +268,27 @@ impl Shell {
             input_clock: 0,
             history: VecDeque::new(),
             history_cursor: 0,
+            history_search: None,
+            search_addon: None,
+            terminal_find: None,
+            serialize_addon: None,
+            recording: None,
+            pager: None,
+            running_script: false,
+            last_result: Vec::new(),
+            previous_result: Vec::new(),
+            saved_results: BTreeMap::new(),
+            grid: None,
             service_url: "http://0.0.0.0:8080".to_string(),
+            service_metadata: BTreeMap::new(),
+            session_params: BTreeMap::new(),
+            connection_state: ConnectionState::Connected,
+            connection_epoch: 0,
+            profiles: Vec::new(),
             service_client: None,
             service_conn: None,
+            catalog: Catalog::default(),
+            completion: None,
         }
     }
 
@@ -119,45 +308,224 @@ impl Shell {
         self.terminal
             .attach_custom_key_event_handler(callback.as_ref().unchecked_ref());
         callback.forget();
+
+        // Register on_data callback for pasted text.
+        // Bracketed paste lets us tell pasted text apart from other terminal input.
+        let callback = Closure::wrap(Box::new(move |data: String| {
+            Shell::on_data(data);
+        }) as Box<dyn FnMut(_)>);
+        self.terminal.on_data(callback.as_ref().unchecked_ref());
+        callback.forget();
+        self.terminal.write(vt100::BRACKETED_PASTE_ON);
+
+        // Load the search addon for finding text in the output
+        let search_addon = SearchAddon::new();
+        let addon: &JsValue = search_addon.as_ref();
+        self.terminal.load_addon(addon.clone().unchecked_into());
+        self.search_addon = Some(search_addon);
+
+        // Load the serialize addon for saving transcripts
+        let serialize_addon = SerializeAddon::new();
+        let addon: &JsValue = serialize_addon.as_ref();
+        self.terminal.load_addon(addon.clone().unchecked_into());
+        self.serialize_addon = Some(serialize_addon);
     }
 
     /// Run initial setup
     pub async fn initial_setup() -> Result<(), js_sys::Error> {
-        // Disconnect any existing connection
-        let conn = Shell::with_mut(|s| s.service_conn.clone());
-        if let Some(ref conn) = conn {
-            let conn_guard = conn.read().unwrap();
-            conn_guard.disconnect().await?;
-        }
+        let (service_url, epoch) = Shell::with(|s| (s.service_url.clone(), s.connection_epoch));
+        let connected = Shell::connect(
+            ConnectionProfile {
+                name: String::new(),
+                url: service_url,
+                metadata: BTreeMap::new(),
+            },
+            epoch,
+        )
+        .await;
+        Shell::with_mut(|s| {
+            s.clear_and_greet();
+            // Start offline if the service is unreachable, .connect retries
+            if let Err(e) = connected {
+                s.write_error("", &QueryError::from(e));
+                s.writeln("Not connected");
+                s.set_connection_state(ConnectionState::Offline);
+            }
+            s.prompt();
+            s.focus();
+        });
+        Ok(())
+    }
 
+    /// Connect to a service.
+    /// The current connection is only replaced once the new one answers, it is kept if connecting fails.
+    /// Returns false if the connection epoch changed in the meantime, the new connection is dropped then.
+    async fn connect(profile: ConnectionProfile, epoch: u64) -> Result<bool, js_sys::Error> {
         // Create service client
-        let (rt_ptr, service_url) = Shell::with_mut(|s| (s.runtime.clone().unwrap(), s.service_url.clone()));
-        let rt = rt_ptr.read().unwrap();
-        let client_js: JsLoperServiceClient = rt.configure_client(&service_url).await.unwrap().into();
-        let client = LoperServiceClient::from_bindings(client_js);
+        let rt_ptr = Shell::with(|s| s.runtime.clone().unwrap());
+        let rt: ShellRuntime = rt_ptr.read().unwrap().clone().unchecked_into();
+        let client_js: JsLoperServiceClient = rt
+            .configure_client(&profile.url, profile.metadata_value())
+            .await?
+            .into();
+        let client = Arc::new(RwLock::new(LoperServiceClient::from_bindings(
+            client_js.clone().unchecked_into(),
+        )));
+        let conn = LoperServiceClient::connect(client.clone()).await?;
+        // Creating the connection does not reach the service, check that it answers
+        conn.ping().await?;
+        Shell::with(|s| {
+            for (key, value) in s.session_params.iter() {
+                conn.set_param(key, value);
+            }
+        });
 
-        // Store new database and reset the connection
-        let client = Shell::with_mut(|s| {
-            let client = Arc::new(RwLock::new(client));
-            s.service_conn = None;
-            s.service_client = Some(client.clone());
-            client
+        // Swap in the new client and connection unless a newer connect took over
+        let swapped = Shell::with_mut(|s| {
+            if s.connection_epoch != epoch {
+                return Err(conn);
+            }
+            rt.use_client(client_js);
+            s.catalog = Catalog::default();
+            s.service_url = profile.url;
+            s.service_metadata = profile.metadata;
+            s.service_client = Some(client);
+            s.set_connection_state(ConnectionState::Connected);
+            Ok(s.service_conn.replace(Arc::new(RwLock::new(conn))))
         });
+        let connected = swapped.is_ok();
+        let old_conn = match swapped {
+            Ok(old_conn) => {
+                spawn_local(Shell::load_catalog());
+                old_conn
+            }
+            Err(conn) => Some(Arc::new(RwLock::new(conn))),
+        };
 
-        let conn = LoperServiceClient::connect(client.clone()).await?;
+        // Disconnect the previous or the dropped connection
+        if let Some(old_conn) = old_conn {
+            let conn_guard = old_conn.read().unwrap();
+            if let Err(e) = conn_guard.disconnect().await {
+                warn!("Failed to disconnect: {:?}", e.to_string());
+            }
+        }
+        Ok(connected)
+    }
+
+    /// Drop the lost connection and start reconnecting (if not already)
+    fn connection_lost() {
+        let epoch = Shell::with_mut(|s| {
+            if s.connection_state == ConnectionState::Reconnecting {
+                return None;
+            }
+            s.service_conn = None;
+            s.connection_epoch += 1;
+            s.set_connection_state(ConnectionState::Reconnecting);
+            Some(s.connection_epoch)
+        });
+        if let Some(epoch) = epoch {
+            spawn_local(Shell::reconnect(epoch));
+        }
+    }
 
-        // Create connection
+    /// Reconnect to the current service with exponential backoff.
+    /// Stops as soon as the connection is changed otherwise.
+    async fn reconnect(epoch: u64) {
+        for attempt in 0..RECONNECT_ATTEMPTS {
+            sleep(reconnect_delay(attempt)).await;
+            let profile = Shell::with(|s| {
+                if s.connection_epoch != epoch {
+                    return None;
+                }
+                Some(ConnectionProfile {
+                    name: String::new(),
+                    url: s.service_url.clone(),
+                    metadata: s.service_metadata.clone(),
+                })
+            });
+            let profile = match profile {
+                Some(profile) => profile,
+                None => return,
+            };
+            let url = profile.url.clone();
+            match Shell::connect(profile, epoch).await {
+                Ok(true) => {
+                    Shell::with_mut(|s| s.notify(&format!("Reconnected to {}", url)));
+                    return;
+                }
+                // The connection was changed otherwise
+                Ok(false) => return,
+                Err(e) => warn!(
+                    "Reconnect attempt {} failed: {:?}",
+                    attempt + 1,
+                    e.to_string()
+                ),
+            }
+        }
         Shell::with_mut(|s| {
-            s.service_conn = Some(Arc::new(RwLock::new(conn)));
-            s.clear_and_greet();
-            s.prompt();
-            s.focus();
+            if s.connection_epoch != epoch {
+                return;
+            }
+            s.set_connection_state(ConnectionState::Offline);
+            s.notify(&format!(
+                "Connection to {} lost, use .connect to retry",
+                s.service_url
+            ));
         });
-        Ok(())
+    }
+
+    /// Update the connection state and show it in the prompt
+    fn set_connection_state(&mut self, state: ConnectionState) {
+        if self.connection_state == state {
+            return;
+        }
+        self.connection_state = state;
+        self.input.set_status(state.status());
+        if self.input_enabled && self.history_search.is_none() {
+            self.input.set_prompt(None);
+            self.input.refresh();
+            self.flush();
+        }
+    }
+
+    /// Write a notification without losing the current input
+    fn notify(&mut self, text: &str) {
+        if self.input_enabled {
+            self.input.print_below(text);
+            self.flush();
+        } else {
+            self.writeln(text);
+        }
+    }
+
+    /// Load the catalog of the connected service for tab completion
+    async fn load_catalog() {
+        let conn = match Shell::with(|s| s.service_conn.clone()) {
+            Some(conn) => conn,
+            None => return,
+        };
+        let batches = match conn
+            .read()
+            .unwrap()
+            .run_query(completion::CATALOG_QUERY)
+            .await
+        {
+            Ok(batches) => batches,
+            Err(e) => {
+                warn!("Failed to load the catalog: {:?}", e.to_string());
+                return;
+            }
+        };
+        let catalog = Catalog::from_batches(&batches);
+        if catalog.is_empty() {
+            info!("Catalog is empty");
+        }
+        Shell::with_mut(|s| s.catalog = catalog);
     }
 
     /// Load input history
-    pub fn load_history(history: Vec<String>, cursor: usize) {
+    pub fn load_history(history: Vec<HistoryEntry>, cursor: usize) {
+        let cursor = std::cmp::min(cursor, history.len());
         let mut h = VecDeque::with_capacity(history.len());
         for entry in &history[cursor..history.len()] {
             h.push_back(entry.clone());
@@ -171,6 +539,30 @@ impl Shell {
         });
     }
 
+    /// Load connection profiles
+    pub fn load_profiles(profiles: Vec<ConnectionProfile>) {
+        Shell::with_mut(|s| s.profiles = profiles);
+    }
+
+    /// Save the connection profiles through the runtime
+    fn save_profiles(&self) {
+        let value = serde_json::to_string(&self.profiles)
+            .ok()
+            .and_then(|json| js_sys::JSON::parse(&json).ok())
+            .unwrap_or(JsValue::NULL);
+        if let Some(ref rt) = self.runtime {
+            let rt_copy = rt.clone();
+            spawn_local(async move {
+                if let Err(e) = rt_copy.read().unwrap().save_profiles(value).await {
+                    warn!(
+                        "Failed to save the connection profiles: {:?}",
+                        e.to_string()
+                    );
+                }
+            });
+        }
+    }
+
     /// Write directly to the terminal
     pub fn write(&self, text: &str) {
         self.terminal.write(text);
@@ -197,8 +589,17 @@ impl Shell {
         self.prompt();
     }
 
-    fn remember_command(&mut self, text: String) {
-        self.history.push_back(text.clone());
+    /// Create a history entry for an input
+    fn history_entry(&self, text: &str) -> HistoryEntry {
+        HistoryEntry::from_input(text, now_timestamp(), &self.service_url)
+    }
+
+    fn remember_command(&mut self, entry: HistoryEntry) {
+        let value = serde_json::to_string(&entry)
+            .ok()
+            .and_then(|json| js_sys::JSON::parse(&json).ok())
+            .unwrap_or(JsValue::NULL);
+        self.history.push_back(entry);
         if self.history.len() > HISTORY_LENGTH {
             self.history.pop_front();
         }
@@ -206,7 +607,7 @@ impl Shell {
         if let Some(ref rt) = self.runtime {
             let rt_copy = rt.clone();
             spawn_local(async move {
-                match rt_copy.read().unwrap().push_input_to_history(&text).await {
+                match rt_copy.read().unwrap().push_history_entry(value).await {
                     Ok(_) => (),
                     Err(_e) => (),
                 }
@@ -221,10 +622,13 @@ impl Shell {
 
         let cmd = &trimmed[..trimmed.find(' ').unwrap_or_else(|| trimmed.len())];
         let args = trimmed[cmd.len()..].trim();
+        let mut success = true;
         match cmd {
             ".clear" => {
                 Shell::with_mut(|s| {
-                    s.remember_command(text.clone());
+                    let mut entry = s.history_entry(&text);
+                    entry.success = Some(true);
+                    s.remember_command(entry);
                     s.clear();
                 });
                 return;
@@ -232,8 +636,44 @@ impl Shell {
             ".help" => Shell::with(|s| {
                 s.write(&format!(
                     concat!(
-                        "┌ .clear            Clear the shell.\r\n",
-                        "└ .output on|off    Print results on or off.\r\n",
+                        "┌ .browse                 Explore the last result in a grid.\r\n",
+                        "├ .chart bar|line <x> <y> Chart the columns of the last result.\r\n",
+                        "├ .chart hist <column> [bins]\r\n",
+                        "│                         Chart the distribution of a column of the last result.\r\n",
+                        "├ .clear                  Clear the shell.\r\n",
+                        "├ .connect [url|name]     Connect to a service url or profile.\r\n",
+                        "├ .copy <row> <column>    Copy the full value of a cell of the last result.\r\n",
+                        "├ .diff [<old> <new>] [key <columns>]\r\n",
+                        "│                         Compare the last two results or saved results.\r\n",
+                        "├ .diff save <name>       Save the last result for comparing it.\r\n",
+                        "├ .find [text]            Find a text in the output, also with ctrl+f.\r\n",
+                        "├ .history [text]         List the history entries containing a text.\r\n",
+                        "├ .maxwidth <n>|off       Truncate longer text values, binary values show 16 bytes.\r\n",
+                        "├ .nested [depth|length] <n>\r\n",
+                        "│                         Limit the depth and length of nested values.\r\n",
+                        "├ .numbers <option> <value>\r\n",
+                        "│                         Format numbers, the options are locale, separators,\r\n",
+                        "│                         precision, scientific and align.\r\n",
+                        "├ .output on|off          Print results on or off.\r\n",
+                        "├ .pager on|auto|off      Show results in a pager, auto for large results.\r\n",
+                        "├ .paste run|edit         Run the statements of pasted scripts or edit them.\r\n",
+                        "├ .ping                   Measure the round-trip latency to the service.\r\n",
+                        "├ .profiles               List the connection profiles.\r\n",
+                        "├ .profiles add <name> <url> [key=value ...]\r\n",
+                        "│                         Add a connection profile with request metadata,\r\n",
+                        "│                         e.g. authorization=\"Bearer <token>\". Profiles are\r\n",
+                        "│                         saved unencrypted in the local storage of the browser.\r\n",
+                        "├ .profiles remove <name>\r\n",
+                        "│                         Remove a connection profile.\r\n",
+                        "├ .record on|off          Record the queries, results and timings and save the log.\r\n",
+                        "├ .summarize              Show statistics of the columns of the last result,\r\n",
+                        "│                         also with SUMMARIZE <query>;\r\n",
+                        "├ .timer on|off|detail    Print the query time, detail splits it into its phases.\r\n",
+                        "├ .timezone [local|UTC|<name>]\r\n",
+                        "│                         Show timestamps in a time zone, e.g. Europe/Berlin.\r\n",
+                        "├ .transcript save [txt|html|ansi]\r\n",
+                        "│                         Save the terminal buffer.\r\n",
+                        "└ !n                      Run the history entry n.\r\n",
                     ),
                 ));
             }),
@@ -249,20 +689,111 @@ impl Shell {
                 }
             }),
             ".timer" => Shell::with_mut(|s| {
-                if args.ends_with("on") {
-                    s.settings.timer = true;
-                    s.writeln("Timer enabled");
-                } else if args.ends_with("off") {
-                    s.settings.timer = false;
-                    s.writeln("Timer disabled");
+                s.settings.timer = match args {
+                    "on" => TimerMode::On,
+                    "detail" => TimerMode::Detail,
+                    "off" => TimerMode::Off,
+                    _ => {
+                        s.writeln("Usage: .timer [on/off/detail]");
+                        return;
+                    }
+                };
+                s.writeln(match s.settings.timer {
+                    TimerMode::On => "Timer enabled",
+                    TimerMode::Detail => "Timer enabled with details",
+                    TimerMode::Off => "Timer disabled",
+                });
+            }),
+            ".pager" => Shell::with_mut(|s| {
+                s.settings.pager = match args {
+                    "on" => PagerMode::On,
+                    "auto" => PagerMode::Auto,
+                    "off" => PagerMode::Off,
+                    _ => {
+                        s.writeln("Usage: .pager [on/auto/off]");
+                        return;
+                    }
+                };
+                s.writeln(match s.settings.pager {
+                    PagerMode::On => "Pager enabled",
+                    PagerMode::Auto => "Pager enabled for large results",
+                    PagerMode::Off => "Pager disabled",
+                });
+            }),
+            ".nested" => success = Shell::with_mut(|s| s.on_nested(args)),
+            ".maxwidth" => success = Shell::with_mut(|s| s.on_max_width(args)),
+            ".copy" => success = Shell::on_copy(args).await,
+            ".chart" => success = Shell::with_mut(|s| s.on_chart(args)),
+            ".diff" => success = Shell::with_mut(|s| s.on_diff(args)),
+            ".summarize" => {
+                success = Shell::with_mut(|s| {
+                    let batches = s.last_result.clone();
+                    s.write_summary(&batches)
+                })
+            }
+            ".numbers" => success = Shell::with_mut(|s| s.on_numbers(args)),
+            ".timezone" => success = Shell::with_mut(|s| s.on_timezone(args)),
+            ".paste" => Shell::with_mut(|s| {
+                if args.ends_with("run") {
+                    s.settings.paste_run = true;
+                    s.writeln("Pasted statements are run");
+                } else if args.ends_with("edit") {
+                    s.settings.paste_run = false;
+                    s.writeln("Pasted statements are edited");
                 } else {
-                    s.writeln("Usage: .timer [on/off]")
+                    s.writeln("Usage: .paste [run/edit]")
                 }
             }),
-            cmd => Shell::with(|s| s.writeln(&format!("Unknown command: {}", &cmd))),
+            ".history" => Shell::with(|s| s.write_history(args)),
+            ".find" => {
+                Shell::with_mut(|s| {
+                    let mut entry = s.history_entry(&text);
+                    entry.success = Some(true);
+                    s.remember_command(entry);
+                });
+                // Don't search the command itself
+                Shell::sync_terminal().await;
+                Shell::with_mut(|s| {
+                    s.terminal_find = Some(TerminalFind {
+                        query: args.to_string(),
+                        options: FindOptions::default(),
+                        end_row: Some(s.cursor_buffer_row().saturating_sub(s.input.rows())),
+                    });
+                    s.prompt();
+                });
+                Shell::find_in_output(true, true).await;
+                return;
+            }
+            ".browse" => {
+                // The prompt follows when the grid is closed
+                let opened = Shell::with_mut(|s| {
+                    if !s.browse() {
+                        return false;
+                    }
+                    let mut entry = s.history_entry(&text);
+                    entry.success = Some(true);
+                    s.remember_command(entry);
+                    true
+                });
+                if opened {
+                    return;
+                }
+                success = false;
+            }
+            ".connect" => success = Shell::on_connect(args).await,
+            ".record" => success = Shell::with_mut(|s| s.on_record(args)),
+            ".transcript" => success = Shell::with(|s| s.save_transcript(args)),
+            ".ping" => success = Shell::on_ping().await,
+            ".profiles" => success = Shell::with_mut(|s| s.on_profiles(args)),
+            cmd => {
+                success = false;
+                Shell::with(|s| s.writeln(&format!("Unknown command: {}", &cmd)))
+            }
         }
         Shell::with_mut(|s| {
-            s.remember_command(text.clone());
+            let mut entry = s.history_entry(&text);
+            entry.success = Some(success);
+            s.remember_command(entry);
             s.writeln("");
             s.prompt();
         });
@@ -270,52 +801,95 @@ impl Shell {
 
     /// Command handler
     async fn on_sql(text: String) {
+        let entry = RefCell::new(Shell::with(|s| s.history_entry(&text)));
+        // Keep the input in the prompt if the query could not reach the service
+        let keep_input = std::cell::Cell::new(false);
         defer!({
             Shell::with_mut(|s| {
-                s.remember_command(text.clone());
+                s.remember_command(entry.take());
+                // The prompt follows when the pager is closed
+                if s.pager.is_some() {
+                    return;
+                }
                 s.writeln("");
                 s.prompt();
+                if keep_input.get() {
+                    s.input.replace(&text);
+                    s.flush();
+                }
             })
         });
 
         // Get the database connection
-        let (maybe_conn, use_timer, terminal_width) = Shell::with(|shell| {
+        let (maybe_conn, terminal_width) = Shell::with_mut(|shell| {
             shell.writeln("");
-            (
-                shell.service_conn.clone(),
-                shell.settings.timer,
-                shell.terminal_width,
-            )
+            shell.record(&format!(
+                "-- {}\nloper> {}",
+                pretty_timestamp(now_timestamp()),
+                text.trim()
+            ));
+            (shell.service_conn.clone(), shell.terminal_width)
         });
         // Lock the connection
         let conn = match maybe_conn {
             Some(ref conn) => conn.read().unwrap(),
             None => {
                 Shell::with_mut(|s| {
-                    s.writeln("Error: connection not set");
+                    s.writeln("Error: not connected");
+                    s.record("Error: not connected");
+                    s.write_connection_state();
                 });
+                let mut entry = entry.borrow_mut();
+                entry.success = Some(false);
+                entry.error = Some("connection not set".to_string());
+                keep_input.set(true);
                 return;
             }
         };
 
-        // Run the query
+        // Run the query, SUMMARIZE <query> shows the statistics of the result instead
+        let summarize = strip_summarize(&text);
+        let query = summarize.unwrap_or(&text);
         let start = now();
-        let batches = match conn.run_query(&text).await {
-            Ok(batches) => batches,
+        let (batches, stats) = match conn.run_query_with_stats(query).await {
+            Ok(result) => result,
             Err(e) => {
-                let mut msg: String = e.message().into();
-                msg = msg.replace("\n", "\r\n");
-                Shell::with_mut(|s| {
-                    s.writeln(&msg);
-                });
+                let error = QueryError::from(e);
+                let mut entry = entry.borrow_mut();
+                entry.elapsed = Some(now() - start);
+                entry.success = Some(false);
+                entry.error = Some(error.message.clone());
+                Shell::with_mut(|s| s.write_error(query, &error));
+                if error.is_connection_lost() {
+                    keep_input.set(true);
+                    Shell::connection_lost();
+                }
                 return;
             }
         };
-        let elapsed = if use_timer {
-            Duration::milliseconds((now() - start) as i64)
-        } else {
-            Duration::milliseconds(0)
-        };
+        {
+            let mut entry = entry.borrow_mut();
+            entry.elapsed = Some(now() - start);
+            entry.rows = Some(batches.iter().map(|b| b.num_rows() as u64).sum());
+            entry.success = Some(true);
+        }
+        Shell::with_mut(|s| {
+            s.previous_result = std::mem::replace(&mut s.last_result, batches.clone())
+        });
+        if is_ddl(&text) {
+            spawn_local(Shell::load_catalog());
+        }
+        let elapsed = Duration::milliseconds((now() - start) as i64);
+
+        if summarize.is_some() {
+            let success = Shell::with_mut(|s| {
+                let success = s.write_summary(&batches);
+                s.record(&format!("Elapsed: {}", pretty_elapsed(&elapsed)));
+                success
+            });
+            entry.borrow_mut().success = Some(success);
+            return;
+        }
 
         // Detect explain result
         if batches.len() == 1 {
@@ -339,6 +913,8 @@ impl Shell {
                     explain = explain.replace("\n", "\r\n");
                     Shell::with_mut(|s| {
                         s.write(&explain);
+                        s.record(&explain);
+                        s.record(&format!("Elapsed: {}", pretty_elapsed(&elapsed)));
                     });
                     return;
                 }
@@ -346,19 +922,49 @@ impl Shell {
         }
 
         Shell::with_mut(|s| {
-            // Print the table
+            // Print the table or show it in the pager
+            let render_start = now();
+            let mut pager = None;
             if s.settings.output {
-                let pretty_table = pretty_format_batches(
-                    &batches,
-                    terminal_width as u16,
-                    UTF8_BORDERS_NO_HORIZONTAL,
-                )
+                let rows = s.terminal.get_rows() as usize;
+                // Pasted scripts continue with the next statement, they are not paged
+                let pager_mode = if s.running_script {
+                    PagerMode::Off
+                } else {
+                    s.settings.pager
+                };
+                let pretty_table = match pager_mode {
+                    PagerMode::Off => pretty_format_batches_with_options(
+                        &batches,
+                        terminal_width as u16,
+                        UTF8_BORDERS_NO_HORIZONTAL,
+                        &s.settings.format,
+                    ),
+                    PagerMode::On | PagerMode::Auto => pretty_format_batches_with_options(
+                        &batches,
+                        PAGER_TABLE_WIDTH,
+                        UTF8_BORDERS_NO_HORIZONTAL,
+                        &s.settings.format,
+                    ),
+                }
                 .unwrap_or_default();
-                s.writeln(&pretty_table);
+                let paged = match pager_mode {
+                    PagerMode::Off => false,
+                    PagerMode::On => true,
+                    PagerMode::Auto => Pager::is_needed(&pretty_table, rows, terminal_width),
+                };
+                if paged {
+                    pager = Some(Pager::new(&pretty_table, rows, terminal_width));
+                } else {
+                    s.writeln(&pretty_table);
+                }
+                s.record(&pretty_table);
             }
+            let render_ms = now() - render_start;
+            s.record(&format!("Elapsed: {}", pretty_elapsed(&elapsed)));
 
             // Print elapsed time (if requested)
-            if s.settings.timer {
+            if s.settings.timer != TimerMode::Off {
                 s.writeln(&format!(
                     "{bold}Elapsed:{normal} {elapsed}",
                     elapsed = pretty_elapsed(&elapsed),
@@ -366,115 +972,1047 @@ impl Shell {
                     normal = vt100::MODES_OFF,
                 ));
             }
+            if s.settings.timer == TimerMode::Detail {
+                let ms = |ms: f64| pretty_elapsed(&Duration::milliseconds(ms as i64));
+                let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+                let details = [
+                    format!(
+                        "Server: {}, transfer: {}, decode: {}, render: {}",
+                        ms(stats.first_chunk_ms),
+                        ms(stats.transfer_ms),
+                        ms(stats.decode_ms),
+                        ms(render_ms),
+                    ),
+                    format!(
+                        "Rows: {}, batches: {}, transferred: {}",
+                        rows,
+                        stats.batches,
+                        pretty_bytes(stats.bytes as f64),
+                    ),
+                ];
+                for detail in details.iter() {
+                    s.writeln(detail);
+                    s.record(detail);
+                }
+            }
+
+            // Switch to the alternate screen, the main screen keeps the elapsed time
+            if let Some(pager) = pager {
+                let mut out = format!("{}{}", vt100::ALT_SCREEN_ON, vt100::CURSOR_HIDE);
+                pager.render(&mut out);
+                s.write(&out);
+                s.pager = Some(pager);
+            }
         });
     }
 
-    /// Flush output buffer to the terminal
-    pub fn flush(&mut self) {
-        self.input.flush(&self.terminal);
+    /// Process a key in the pager
+    fn on_pager_key(event: &KeyEvent) {
+        Shell::with_mut(|s| {
+            let action = match s.pager {
+                Some(ref mut pager) => pager.on_key(event),
+                None => return,
+            };
+            match action {
+                PagerAction::None => {}
+                PagerAction::Redraw => {
+                    let mut out = String::new();
+                    if let Some(ref pager) = s.pager {
+                        pager.render(&mut out);
+                    }
+                    s.write(&out);
+                }
+                PagerAction::Quit => {
+                    s.pager = None;
+                    s.write(&format!("{}{}", vt100::CURSOR_SHOW, vt100::ALT_SCREEN_OFF));
+                    s.writeln("");
+                    s.prompt();
+                }
+            }
+        });
     }
 
-    /// Highlight input text (if sql)
-    fn highlight_input() {
-        let (input, input_clock) = Shell::with_mut(|s| (s.input.collect(), s.input_clock));
-        if input.trim_start().starts_with('.') {
-            return;
+    /// Show or change the limits of nested values
+    fn on_nested(&mut self, args: &str) -> bool {
+        let mut parts = args.split_whitespace();
+        let (option, value) = (parts.next(), parts.next().map(|v| v.parse::<usize>()));
+        match (option, value) {
+            (None, _) => {}
+            (Some("depth"), Some(Ok(n))) if n > 0 => self.settings.format.max_depth = n,
+            (Some("length"), Some(Ok(n))) if n > 0 => self.settings.format.max_length = n,
+            _ => {
+                self.writeln("Usage: .nested [depth/length] <n>");
+                return false;
+            }
         }
-        let db_ptr = Shell::with(|s| s.service_client.clone()).unwrap();
-        spawn_local(async move {
-            let db = match db_ptr.read() {
-                Ok(guard) => guard,
-                Err(_) => return,
-            };
-            let tokens = match db.tokenize(&input).await {
-                Ok(t) => t,
-                Err(_) => return,
-            };
-            Shell::with_mut(|s| {
-                if s.input_clock != input_clock {
-                    return;
+        self.writeln(&format!(
+            "Nested values are shown up to depth {} and {} elements",
+            self.settings.format.max_depth, self.settings.format.max_length
+        ));
+        true
+    }
+
+    /// Show or change the maximum width of text values
+    fn on_max_width(&mut self, args: &str) -> bool {
+        match args.trim() {
+            "" => {}
+            "off" => self.settings.format.max_width = None,
+            n => match n.parse::<usize>() {
+                Ok(n) if n > 0 => self.settings.format.max_width = Some(n),
+                _ => {
+                    self.writeln("Usage: .maxwidth [<n>/off]");
+                    return false;
                 }
-                s.input.highlight_sql(tokens);
-                s.flush();
-            });
+            },
+        }
+        self.writeln(&match self.settings.format.max_width {
+            Some(n) => format!("Text values are truncated after {} columns", n),
+            None => "Text values are shown in full".to_string(),
         });
+        true
     }
 
-    /// Process on-key event
-    fn on_key(keyboard_event: web_sys::KeyboardEvent) {
-        if !Shell::with(|s| s.input_enabled) {
-            return;
+    /// Print the statistics of the columns of record batches.
+    /// Returns false if there is no result to summarize.
+    fn write_summary(&mut self, batches: &[RecordBatch]) -> bool {
+        if batches.is_empty() {
+            self.writeln("No result to summarize");
+            return false;
         }
-        if &keyboard_event.type_() != "keydown" {
-            return;
+        let table = summary::summarize(batches, &self.settings.format).and_then(|summary| {
+            pretty_format_batches_with_options(
+                &[summary],
+                self.terminal_width as u16,
+                UTF8_BORDERS_NO_HORIZONTAL,
+                &self.settings.format,
+            )
+        });
+        match table {
+            Ok(table) => {
+                self.writeln(&table);
+                self.record(&table);
+                true
+            }
+            Err(e) => {
+                self.writeln(&format!("Error: {}", e));
+                false
+            }
         }
-        let event = KeyEvent::from_event(keyboard_event.clone());
-        match event.key {
-            Key::Enter => {
-                let input = Shell::with_mut(|s| {
-                    s.input_clock += 1;
-                    s.input.collect()
-                });
-                // Is a command?
-                if input.trim_start().starts_with('.') {
-                    Shell::with_mut(|s| s.block_input());
-                    spawn_local(Shell::on_command(input));
-                } else {
-                    // Ends with semicolon?
-                    if input.trim_end().ends_with(';') {
-                        Shell::with_mut(|s| s.block_input());
-                        spawn_local(Shell::on_sql(input));
-                    } else {
-                        Shell::with_mut(|s| {
-                            s.input.consume(event);
-                            s.flush();
-                        });
-                    }
-                }
+    }
+
+    /// Chart columns of the last result
+    fn on_chart(&mut self, args: &str) -> bool {
+        let mut parts = args.split_whitespace();
+        let (kind, x, y) = match (
+            parts.next().and_then(ChartKind::parse),
+            parts.next(),
+            parts.next(),
+        ) {
+            (Some(kind), Some(x), y) => (kind, x, y),
+            _ => {
+                self.writeln("Usage: .chart [bar/line] <x> <y> or .chart hist <column> [bins]");
+                return false;
             }
-            Key::ArrowUp => {
-                let should_highlight = Shell::with_mut(|s| -> bool {
-                    if s.history_cursor > 0 {
-                        s.history_cursor -= 1;
-                        s.input_clock += 1;
-                        s.input.replace(&s.history[s.history_cursor]);
-                        s.flush();
-                        return true;
-                    }
-                    false
-                });
-                if should_highlight {
-                    Shell::highlight_input();
-                }
+        };
+        let rows = chart::line_chart_rows(self.terminal.get_rows() as usize);
+        match chart::render_chart(
+            kind,
+            &self.last_result,
+            x,
+            y,
+            self.terminal_width,
+            rows,
+            &self.settings.format,
+        ) {
+            Ok(chart) => {
+                self.writeln(&chart);
+                self.record(&chart);
+                true
             }
-            Key::ArrowDown => {
-                let should_highlight = Shell::with_mut(|s| -> bool {
-                    if s.history_cursor < s.history.len() {
-                        s.history_cursor += 1;
-                        s.input.replace(if s.history_cursor < s.history.len() {
-                            s.history[s.history_cursor].as_str()
-                        } else {
-                            ""
-                        });
-                        s.input_clock += 1;
-                        s.flush();
-                        return true;
-                    }
-                    false
-                });
-                if should_highlight {
-                    Shell::highlight_input();
-                }
+            Err(e) => {
+                self.writeln(&e);
+                false
             }
-            Key::Backspace => {
-                Shell::with_mut(|s| {
+        }
+    }
+
+    /// Get a result by name, last and previous are the last two results
+    fn named_result(&self, name: &str) -> Option<&Vec<RecordBatch>> {
+        match name {
+            "last" => Some(&self.last_result),
+            "previous" => Some(&self.previous_result),
+            name => self.saved_results.get(name),
+        }
+    }
+
+    /// Save the last result or compare two results
+    fn on_diff(&mut self, args: &str) -> bool {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let (names, keys) = match parts.as_slice() {
+            ["save", name] => {
+                if self.last_result.is_empty() {
+                    self.writeln("No result to save");
+                    return false;
+                }
+                self.saved_results
+                    .insert(name.to_string(), self.last_result.clone());
+                self.writeln(&format!("Saved the last result as {}", name));
+                return true;
+            }
+            [] => (("previous", "last"), None),
+            ["key", keys] => (("previous", "last"), Some(*keys)),
+            [old, new] => ((*old, *new), None),
+            [old, new, "key", keys] => ((*old, *new), Some(*keys)),
+            _ => {
+                self.writeln("Usage: .diff [<old> <new>] [key <column>,...] or .diff save <name>");
+                return false;
+            }
+        };
+        let results = (self.named_result(names.0), self.named_result(names.1));
+        let (old, new) = match results {
+            (Some(old), Some(new)) if !old.is_empty() && !new.is_empty() => (old, new),
+            _ => {
+                self.writeln("No results to compare");
+                return false;
+            }
+        };
+        let keys: Vec<String> = keys
+            .map(|k| k.split(',').map(|k| k.to_string()).collect())
+            .unwrap_or_default();
+        let diff = diff_results(old, new, &keys, &self.settings.format);
+        match diff {
+            Ok(diff) => {
+                let out = diff.render(self.terminal_width as u16, UTF8_BORDERS_NO_HORIZONTAL);
+                self.writeln(&out);
+                self.record(&out);
+                true
+            }
+            Err(e) => {
+                self.writeln(&e);
+                false
+            }
+        }
+    }
+
+    /// Get the full value of a cell of the last result.
+    /// The row is a number starting at 1, the column a name or a number starting at 1.
+    fn last_result_value(&self, row: &str, column: &str) -> Result<String, String> {
+        let schema = match self.last_result.first() {
+            Some(batch) => batch.schema(),
+            None => return Err("No result to copy from".to_string()),
+        };
+        let column_index = schema
+            .fields()
+            .iter()
+            .position(|f| f.name() == column)
+            .or_else(|| match column.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= schema.fields().len() => Some(n - 1),
+                _ => None,
+            })
+            .ok_or_else(|| format!("Unknown column: {}", column))?;
+        let mut row_index = match row.parse::<usize>() {
+            Ok(n) if n >= 1 => n - 1,
+            _ => return Err(format!("Invalid row: {}", row)),
+        };
+        for batch in self.last_result.iter() {
+            if row_index < batch.num_rows() {
+                return format_full_value(
+                    batch.column(column_index),
+                    row_index,
+                    &self.settings.format,
+                )
+                .map_err(|e| e.to_string());
+            }
+            row_index -= batch.num_rows();
+        }
+        Err(format!("Invalid row: {}", row))
+    }
+
+    /// Copy the full value of a cell of the last result to the clipboard
+    async fn on_copy(args: &str) -> bool {
+        let mut parts = args.split_whitespace();
+        let (row, column) = match (parts.next(), parts.next()) {
+            (Some(row), Some(column)) => (row, column),
+            _ => {
+                Shell::with(|s| s.writeln("Usage: .copy <row> <column>"));
+                return false;
+            }
+        };
+        let (value, rt) = Shell::with(|s| (s.last_result_value(row, column), s.runtime.clone()));
+        let value = match value {
+            Ok(value) => value,
+            Err(e) => {
+                Shell::with(|s| s.writeln(&e));
+                return false;
+            }
+        };
+        let rt = match rt {
+            Some(rt) => rt,
+            None => return false,
+        };
+        // Clone the runtime handle to not hold the lock while waiting for the clipboard
+        let rt: ShellRuntime = rt.read().unwrap().clone().unchecked_into();
+        let result = rt.write_clipboard_text(&value).await;
+        match result {
+            Ok(()) => {
+                Shell::with(|s| {
+                    s.writeln(&format!(
+                        "Copied {} characters to the clipboard",
+                        value.chars().count()
+                    ))
+                });
+                true
+            }
+            Err(e) => {
+                Shell::with(|s| {
+                    s.writeln(&format!("Failed to write to clipboard: {}", e.to_string()))
+                });
+                false
+            }
+        }
+    }
+
+    /// Show or change the format of numbers
+    fn on_numbers(&mut self, args: &str) -> bool {
+        let mut numbers = self.settings.format.numbers;
+        let mut parts = args.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (None, _) => {}
+            (Some("locale"), Some(locale)) => match locale_separators(locale) {
+                Some((group, decimal)) => {
+                    numbers.group_separator = Some(group);
+                    numbers.decimal_separator = decimal;
+                }
+                None => {
+                    self.writeln(&format!("Unknown locale: {}", locale));
+                    return false;
+                }
+            },
+            (Some("separators"), Some("on")) => {
+                let group = if numbers.decimal_separator == ',' {
+                    '.'
+                } else {
+                    ','
+                };
+                numbers.group_separator = Some(group);
+            }
+            (Some("separators"), Some("off")) => numbers.group_separator = None,
+            (Some("precision"), Some("auto")) => numbers.precision = None,
+            (Some("precision"), Some(n)) if n.parse::<usize>().is_ok() => {
+                numbers.precision = n.parse().ok();
+            }
+            (Some("scientific"), Some("off")) => numbers.scientific_exponent = None,
+            (Some("scientific"), Some(n)) if n.parse::<u32>().map(|n| n > 0).unwrap_or(false) => {
+                numbers.scientific_exponent = n.parse().ok();
+            }
+            (Some("align"), Some("decimal")) => numbers.align_decimal = true,
+            (Some("align"), Some("right")) => numbers.align_decimal = false,
+            (Some("reset"), None) => numbers = NumberFormat::default(),
+            _ => {
+                self.writeln(concat!(
+                    "Usage: .numbers [locale <name>/separators on|off/precision <n>|auto",
+                    "/scientific <exponent>|off/align decimal|right/reset]"
+                ));
+                return false;
+            }
+        }
+        self.settings.format.numbers = numbers;
+        let group = match numbers.group_separator {
+            Some(separator) => format!("'{}'", separator),
+            None => "none".to_string(),
+        };
+        let precision = match numbers.precision {
+            Some(precision) => format!("{} digits", precision),
+            None => "auto".to_string(),
+        };
+        let scientific = match numbers.scientific_exponent {
+            Some(exponent) => format!("from 1e{} and below 1e-{}", exponent, exponent - 1),
+            None => "off".to_string(),
+        };
+        self.write(&format!(
+            concat!(
+                "Group separator: {group}{endl}",
+                "Decimal separator: '{decimal}'{endl}",
+                "Precision: {precision}{endl}",
+                "Scientific notation: {scientific}{endl}",
+                "Alignment: {alignment}{endl}",
+            ),
+            group = group,
+            decimal = numbers.decimal_separator,
+            precision = precision,
+            scientific = scientific,
+            alignment = if numbers.align_decimal {
+                "decimal separator"
+            } else {
+                "right"
+            },
+            endl = vt100::CRLF,
+        ));
+        true
+    }
+
+    /// Show or change the time zone of timestamps
+    fn on_timezone(&mut self, args: &str) -> bool {
+        let name = args.trim();
+        if !name.is_empty() {
+            let time_zone = if name.eq_ignore_ascii_case("utc") {
+                DisplayTimeZone::utc()
+            } else {
+                let name = if name == "local" {
+                    local_time_zone()
+                } else {
+                    name.to_string()
+                };
+                if zone_offset(&name, now_timestamp() as i64).is_none() {
+                    self.writeln(&format!("Unknown time zone: {}", name));
+                    return false;
+                }
+                DisplayTimeZone {
+                    name,
+                    offset: Some(zone_offset),
+                }
+            };
+            // Let the service evaluate time zone dependent functions in the same time zone
+            self.session_params
+                .insert("time_zone".to_string(), time_zone.name.clone());
+            if let Some(ref conn) = self.service_conn {
+                conn.read().unwrap().set_param("time_zone", &time_zone.name);
+            }
+            self.settings.format.time_zone = time_zone;
+        }
+        let time_zone = &self.settings.format.time_zone;
+        let offset = time_zone
+            .offset
+            .and_then(|offset| offset(&time_zone.name, now_timestamp() as i64))
+            .unwrap_or(0);
+        let sign = if offset < 0 { '-' } else { '+' };
+        self.writeln(&format!(
+            "Time zone: {} (UTC{}{:02}:{:02})",
+            time_zone.name,
+            sign,
+            offset.abs() / 3600,
+            offset.abs() % 3600 / 60
+        ));
+        true
+    }
+
+    /// Open the grid for the last result.
+    /// Returns false if there is no result to explore.
+    fn browse(&mut self) -> bool {
+        if self.last_result.is_empty() {
+            self.writeln("No result to browse");
+            return false;
+        }
+        let rows = self.terminal.get_rows() as usize;
+        let grid = match ResultGrid::new(
+            &self.last_result,
+            &self.settings.format,
+            rows,
+            self.terminal_width,
+        ) {
+            Ok(grid) => grid,
+            Err(e) => {
+                self.writeln(&format!("Error: {}", e));
+                return false;
+            }
+        };
+        let mut out = format!(
+            "{}{}{}",
+            vt100::ALT_SCREEN_ON,
+            vt100::CURSOR_HIDE,
+            vt100::MOUSE_TRACKING_ON
+        );
+        grid.render(&mut out);
+        self.write(&out);
+        self.grid = Some(grid);
+        true
+    }
+
+    /// Apply the action of an input in the grid
+    fn on_grid_action(&mut self, action: GridAction) {
+        match action {
+            GridAction::None => {}
+            GridAction::Redraw => {
+                let mut out = String::new();
+                if let Some(ref grid) = self.grid {
+                    grid.render(&mut out);
+                }
+                self.write(&out);
+            }
+            GridAction::Quit => {
+                self.grid = None;
+                self.write(&format!(
+                    "{}{}{}",
+                    vt100::MOUSE_TRACKING_OFF,
+                    vt100::CURSOR_SHOW,
+                    vt100::ALT_SCREEN_OFF
+                ));
+                self.writeln("");
+                self.prompt();
+            }
+        }
+    }
+
+    /// Run a history entry referenced as !n
+    fn on_history_reference(input: String) {
+        let text = Shell::with(|s| {
+            input.trim()[1..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| s.history.get(i))
+                .map(|e| e.input.clone())
+        });
+        let text = match text {
+            Some(text) => text,
+            None => {
+                Shell::with_mut(|s| {
+                    s.writeln("");
+                    s.writeln(&format!("Unknown history entry: {}", input.trim()));
+                    s.prompt();
+                });
+                return;
+            }
+        };
+        Shell::with_mut(|s| {
+            s.input_clock += 1;
+            s.input.replace(&text);
+            s.flush();
+        });
+        if text.trim_start().starts_with('.') {
+            spawn_local(Shell::on_command(text));
+        } else {
+            spawn_local(Shell::on_sql(text));
+        }
+    }
+
+    /// Write a query error with the SQLSTATE, the detail, the hint and a caret under the error position
+    fn write_error(&mut self, query: &str, error: &QueryError) {
+        let mut out = format!(
+            "{red}{bold}ERROR{sqlstate}:{normal} {message}",
+            red = vt100::COLOR_FG_RED,
+            bold = vt100::MODE_BOLD,
+            normal = vt100::MODES_OFF,
+            sqlstate = error
+                .sqlstate
+                .as_ref()
+                .map(|s| format!(" {}", s))
+                .unwrap_or_default(),
+            message = error.message.replace('\n', vt100::CRLF),
+        );
+
+        // Echo the line of the error with a caret under the offending token
+        let prefix_width = format!("LINE {}: ", query.lines().count()).len();
+        let max_width = self.terminal_width.saturating_sub(prefix_width + 1);
+        if let Some(line) = error.error_line(query, max_width) {
+            let prefix = format!("LINE {}: ", line.line);
+            out.push_str(&format!(
+                "{endl}{prefix}{text}{endl}{indent}{red}{bold}^{underline}{normal}",
+                endl = vt100::CRLF,
+                prefix = prefix,
+                text = line.text,
+                indent = " ".repeat(prefix.len() + line.caret_column),
+                red = vt100::COLOR_FG_RED,
+                bold = vt100::MODE_BOLD,
+                underline = "~".repeat(line.caret_width - 1),
+                normal = vt100::MODES_OFF,
+            ));
+        }
+        for (label, text) in [("DETAIL", &error.detail), ("HINT", &error.hint)].iter() {
+            if let Some(text) = text {
+                out.push_str(&format!(
+                    "{endl}{bold}{label}:{normal} {text}",
+                    endl = vt100::CRLF,
+                    bold = vt100::MODE_BOLD,
+                    label = label,
+                    normal = vt100::MODES_OFF,
+                    text = text.replace('\n', vt100::CRLF),
+                ));
+            }
+        }
+        self.writeln(&out);
+        self.record(&out);
+    }
+
+    /// Append output to the session log (if recording)
+    fn record(&mut self, text: &str) {
+        if let Some(ref mut log) = self.recording {
+            log.push_str(&transcript::strip_ansi(text));
+            log.push('\n');
+        }
+    }
+
+    /// Start or stop recording the session log.
+    /// The log is downloaded when the recording stops.
+    fn on_record(&mut self, args: &str) -> bool {
+        match args {
+            "on" if self.recording.is_some() => self.writeln("Already recording"),
+            "on" => {
+                self.recording = Some(format!(
+                    "-- Session recorded at {} on {}\n",
+                    pretty_timestamp(now_timestamp()),
+                    self.service_url
+                ));
+                self.writeln("Recording started");
+            }
+            "off" => match self.recording.take() {
+                Some(log) => {
+                    let name = format!("loper-session-{}.log", file_timestamp());
+                    self.writeln(&format!("Recording saved to {}", name));
+                    self.download(name, log, "text/plain");
+                }
+                None => self.writeln("Not recording"),
+            },
+            _ => {
+                self.writeln("Usage: .record [on/off]");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Save the terminal buffer as text, html or with the escape sequences
+    fn save_transcript(&self, args: &str) -> bool {
+        let format = match args.strip_prefix("save") {
+            Some(rest) => TranscriptFormat::parse(rest.trim()),
+            None => None,
+        };
+        let (format, addon) = match (format, self.serialize_addon.as_ref()) {
+            (Some(format), Some(addon)) => (format, addon),
+            _ => {
+                self.writeln("Usage: .transcript save [txt/html/ansi]");
+                return false;
+            }
+        };
+        let name = format!(
+            "loper-transcript-{}.{}",
+            file_timestamp(),
+            format.extension()
+        );
+        let content = match format {
+            TranscriptFormat::Text => {
+                let buffer = self.terminal.get_buffer().get_normal();
+                let lines = terminal_search::read_lines(&buffer, buffer.get_length() as usize);
+                let mut text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+                while text.last().map(|l| l.trim().is_empty()).unwrap_or(false) {
+                    text.pop();
+                }
+                text.join("\n") + "\n"
+            }
+            TranscriptFormat::Html => transcript::ansi_to_html(&addon.serialize(None), &name),
+            TranscriptFormat::Ansi => addon.serialize(None),
+        };
+        self.writeln(&format!("Transcript saved to {}", name));
+        self.download(name, content, format.mime_type());
+        true
+    }
+
+    /// Download a file through the runtime
+    fn download(&self, name: String, content: String, mime_type: &'static str) {
+        if let Some(ref rt) = self.runtime {
+            let rt_copy = rt.clone();
+            spawn_local(async move {
+                let rt = rt_copy.read().unwrap();
+                if let Err(e) = rt.download_file(&name, &content, mime_type).await {
+                    warn!("Failed to download {}: {:?}", name, e.to_string());
+                }
+            });
+        }
+    }
+
+    /// Connect to a service url or a named profile
+    /// Reconnects to the current service if the shell is offline.
+    async fn on_connect(args: &str) -> bool {
+        let profile = Shell::with(|s| {
+            if args.is_empty() {
+                if s.connection_state != ConnectionState::Offline {
+                    return None;
+                }
+                return Some(ConnectionProfile {
+                    name: String::new(),
+                    url: s.service_url.clone(),
+                    metadata: s.service_metadata.clone(),
+                });
+            }
+            Some(
+                s.profiles
+                    .iter()
+                    .find(|p| p.name == args)
+                    .cloned()
+                    .unwrap_or_else(|| ConnectionProfile {
+                        name: String::new(),
+                        url: args.to_string(),
+                        metadata: BTreeMap::new(),
+                    }),
+            )
+        });
+        let profile = match profile {
+            Some(profile) => profile,
+            None => {
+                Shell::with(|s| s.write_connection_state());
+                return true;
+            }
+        };
+        let url = profile.url.clone();
+        let epoch = Shell::with_mut(|s| {
+            // Stop pending reconnects
+            s.connection_epoch += 1;
+            s.writeln(&format!("Connecting to {}", url));
+            s.connection_epoch
+        });
+        match Shell::connect(profile, epoch).await {
+            Ok(true) => {
+                Shell::with(|s| s.writeln(&format!("Connected to {}", url)));
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                Shell::with_mut(|s| {
+                    s.write_error("", &QueryError::from(e));
+                    if s.connection_state == ConnectionState::Connected {
+                        s.writeln(&format!("Still connected to {}", s.service_url));
+                    } else {
+                        // The pending reconnects were stopped
+                        s.writeln("Not connected");
+                        s.set_connection_state(ConnectionState::Offline);
+                    }
+                });
+                false
+            }
+        }
+    }
+
+    /// Write the state of the connection
+    fn write_connection_state(&self) {
+        self.writeln(&match self.connection_state {
+            ConnectionState::Connected => format!("Connected to {}", self.service_url),
+            ConnectionState::Reconnecting => format!("Reconnecting to {}", self.service_url),
+            ConnectionState::Offline => format!("Offline, last connected to {}", self.service_url),
+        });
+    }
+
+    /// Measure the round-trip latency to the service
+    async fn on_ping() -> bool {
+        let (conn, url) = Shell::with(|s| (s.service_conn.clone(), s.service_url.clone()));
+        let conn = match conn {
+            Some(conn) => conn,
+            None => {
+                Shell::with(|s| s.write_connection_state());
+                return false;
+            }
+        };
+        let mut times = Vec::with_capacity(PING_COUNT);
+        for _ in 0..PING_COUNT {
+            let start = now();
+            let result = conn.read().unwrap().ping().await;
+            match result {
+                Ok(()) => {
+                    let ms = now() - start;
+                    Shell::with(|s| s.writeln(&format!("Reply from {}: time={:.1} ms", url, ms)));
+                    times.push(ms);
+                }
+                Err(e) => {
+                    let error = QueryError::from(e);
+                    Shell::with_mut(|s| s.write_error("", &error));
+                    if error.is_connection_lost() {
+                        Shell::connection_lost();
+                    }
+                    return false;
+                }
+            }
+        }
+        let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = times.iter().cloned().fold(0.0, f64::max);
+        let avg = times.iter().sum::<f64>() / times.len() as f64;
+        Shell::with(|s| {
+            s.writeln(&format!(
+                "round-trip min/avg/max = {:.1}/{:.1}/{:.1} ms",
+                min, avg, max
+            ))
+        });
+        true
+    }
+
+    /// Manage the connection profiles
+    fn on_profiles(&mut self, args: &str) -> bool {
+        let cmd = &args[..args.find(' ').unwrap_or(args.len())];
+        let rest = args[cmd.len()..].trim();
+        match cmd {
+            "" => self.write_profiles(),
+            "add" => match ConnectionProfile::parse(rest) {
+                Some(profile) => {
+                    self.writeln(&format!("Saved profile {}", profile.name));
+                    if !profile.metadata.is_empty() {
+                        self.writeln(
+                            "The metadata is stored unencrypted in the local storage of the browser",
+                        );
+                    }
+                    match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+                        Some(p) => *p = profile,
+                        None => self.profiles.push(profile),
+                    }
+                    self.save_profiles();
+                }
+                None => {
+                    self.writeln("Usage: .profiles add <name> <url> [key=value ...]");
+                    return false;
+                }
+            },
+            "remove" if !rest.is_empty() => {
+                let count = self.profiles.len();
+                self.profiles.retain(|p| p.name != rest);
+                if self.profiles.len() == count {
+                    self.writeln(&format!("Unknown profile: {}", rest));
+                    return false;
+                }
+                self.writeln(&format!("Removed profile {}", rest));
+                self.save_profiles();
+            }
+            _ => {
+                self.writeln("Usage: .profiles [add <name> <url> [key=value ...]|remove <name>]");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Write the connection profiles.
+    /// Metadata values are hidden since they often contain credentials.
+    fn write_profiles(&self) {
+        if self.profiles.is_empty() {
+            self.writeln("No connection profiles");
+            return;
+        }
+        let mut table = Table::new();
+        table.load_preset(UTF8_BORDERS_NO_HORIZONTAL);
+        table.set_table_width(self.terminal_width as u16);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(Row::from(vec!["Name", "Url", "Metadata", "Active"]));
+        for profile in self.profiles.iter() {
+            let metadata: Vec<String> = profile
+                .metadata
+                .keys()
+                .map(|key| format!("{}=***", key))
+                .collect();
+            table.add_row(Row::from(vec![
+                Cell::new(&profile.name),
+                Cell::new(&profile.url),
+                Cell::new(metadata.join(" ")),
+                Cell::new(if profile.url == self.service_url {
+                    "*"
+                } else {
+                    ""
+                }),
+            ]));
+        }
+        self.writeln(&table.to_string());
+    }
+
+    /// Write the history entries that contain a text
+    fn write_history(&self, pattern: &str) {
+        let pattern = pattern.to_lowercase();
+        let mut table = Table::new();
+        table.load_preset(UTF8_BORDERS_NO_HORIZONTAL);
+        table.set_table_width(self.terminal_width as u16);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(Row::from(vec![
+            "#", "Time", "Elapsed", "Rows", "Status", "Remote", "Input",
+        ]));
+        let mut matches = 0;
+        for (i, entry) in self.history.iter().enumerate() {
+            if !entry.input.to_lowercase().contains(&pattern) {
+                continue;
+            }
+            matches += 1;
+            let elapsed = entry
+                .elapsed
+                .map(|ms| pretty_elapsed(&Duration::milliseconds(ms as i64)))
+                .unwrap_or_default();
+            let status = match (entry.success, &entry.error) {
+                (Some(false), Some(e)) => {
+                    format!("error: {}", e.lines().next().unwrap_or_default())
+                }
+                (Some(false), None) => "error".to_string(),
+                (Some(true), _) => "ok".to_string(),
+                (None, _) => String::new(),
+            };
+            table.add_row(Row::from(vec![
+                Cell::new(i + 1).set_alignment(CellAlignment::Right),
+                Cell::new(if entry.timestamp > 0.0 {
+                    pretty_timestamp(entry.timestamp)
+                } else {
+                    String::new()
+                }),
+                Cell::new(elapsed).set_alignment(CellAlignment::Right),
+                Cell::new(entry.rows.map(|r| r.to_string()).unwrap_or_default())
+                    .set_alignment(CellAlignment::Right),
+                Cell::new(status),
+                Cell::new(entry.url.clone().unwrap_or_default()),
+                Cell::new(entry.input.trim()),
+            ]));
+        }
+        if matches == 0 {
+            self.writeln("No matching history entries");
+            return;
+        }
+        self.writeln(&table.to_string());
+    }
+
+    /// Flush output buffer to the terminal
+    pub fn flush(&mut self) {
+        self.input.flush(&self.terminal);
+    }
+
+    /// Highlight input text (if sql).
+    /// The prompt buffer tokenizes locally, this upgrades to the service tokens if a client is connected.
+    fn highlight_input() {
+        let (input, input_clock) = Shell::with_mut(|s| (s.input.collect(), s.input_clock));
+        if input.trim_start().starts_with('.') {
+            return;
+        }
+        let db_ptr = match Shell::with(|s| s.service_client.clone()) {
+            Some(db) => db,
+            None => return,
+        };
+        spawn_local(async move {
+            let db = match db_ptr.read() {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            let tokens = match db.tokenize(&input).await {
+                Ok(t) => t,
+                Err(_) => return,
+            };
+            // The service does not tokenize, keep the local tokens
+            if tokens.offsets.is_empty() {
+                return;
+            }
+            Shell::with_mut(|s| {
+                if s.input_clock != input_clock {
+                    return;
+                }
+                s.input.highlight_sql(tokens);
+                s.flush();
+            });
+        });
+    }
+
+    /// Process on-key event
+    fn on_key(keyboard_event: web_sys::KeyboardEvent) {
+        if &keyboard_event.type_() != "keydown" {
+            return;
+        }
+        let event = KeyEvent::from_event(keyboard_event.clone());
+        if Shell::with(|s| s.pager.is_some()) {
+            Shell::on_pager_key(&event);
+            return;
+        }
+        if Shell::with(|s| s.grid.is_some()) {
+            Shell::with_mut(|s| {
+                let action = match s.grid {
+                    Some(ref mut grid) => grid.on_key(&event),
+                    None => return,
+                };
+                s.on_grid_action(action);
+            });
+            return;
+        }
+        if !Shell::with(|s| s.input_enabled) {
+            return;
+        }
+        if Shell::with(|s| s.history_search.is_some())
+            && Shell::on_search_key(&keyboard_event, &event)
+        {
+            return;
+        }
+        if Shell::with(|s| s.terminal_find.is_some()) && Shell::on_find_key(&keyboard_event, &event)
+        {
+            return;
+        }
+        match event.key {
+            Key::Enter => {
+                let input = Shell::with_mut(|s| {
+                    s.input_clock += 1;
+                    s.input.collect()
+                });
+                // Is a history reference?
+                if input.trim_start().starts_with('!') {
+                    Shell::with_mut(|s| s.block_input());
+                    Shell::on_history_reference(input);
+                } else if input.trim_start().starts_with('.') {
+                    Shell::with_mut(|s| s.block_input());
+                    spawn_local(Shell::on_command(input));
+                } else {
+                    // Ends with semicolon?
+                    if input.trim_end().ends_with(';') {
+                        Shell::with_mut(|s| s.block_input());
+                        spawn_local(Shell::on_sql(input));
+                    } else {
+                        Shell::with_mut(|s| {
+                            s.input.consume(event);
+                            s.flush();
+                        });
+                    }
+                }
+            }
+            Key::Tab => {
+                Shell::complete_input();
+            }
+            Key::ArrowUp => {
+                let should_highlight = Shell::with_mut(|s| -> bool {
+                    // Move between the lines of the input first
+                    if s.input.move_cursor_up() {
+                        s.input_clock += 1;
+                        s.flush();
+                        return false;
+                    }
+                    if s.history_cursor > 0 {
+                        s.history_cursor -= 1;
+                        s.input_clock += 1;
+                        s.input.replace(&s.history[s.history_cursor].input);
+                        s.flush();
+                        return true;
+                    }
+                    false
+                });
+                if should_highlight {
+                    Shell::highlight_input();
+                }
+            }
+            Key::ArrowDown => {
+                let should_highlight = Shell::with_mut(|s| -> bool {
+                    // Move between the lines of the input first
+                    if s.input.move_cursor_down() {
+                        s.input_clock += 1;
+                        s.flush();
+                        return false;
+                    }
+                    if s.history_cursor < s.history.len() {
+                        s.history_cursor += 1;
+                        s.input.replace(if s.history_cursor < s.history.len() {
+                            s.history[s.history_cursor].input.as_str()
+                        } else {
+                            ""
+                        });
+                        s.input_clock += 1;
+                        s.flush();
+                        return true;
+                    }
+                    false
+                });
+                if should_highlight {
+                    Shell::highlight_input();
+                }
+            }
+            Key::Backspace => {
+                Shell::with_mut(|s| {
                     s.input_clock += 1;
                     s.input.consume(event);
                     s.flush();
                 });
                 Shell::highlight_input();
             }
-            Key::ArrowLeft | Key::ArrowRight => {
+            Key::ArrowLeft | Key::ArrowRight | Key::Home | Key::End => {
                 Shell::with_mut(|s| {
                     s.input_clock += 1;
                     s.input.consume(event);
@@ -482,7 +2020,11 @@ impl Shell {
                 });
             }
             _ => {
-                if keyboard_event.ctrl_key() || keyboard_event.meta_key() {
+                if event.ctrl {
+                    // Read the clipboard ourselves instead of pasting through the browser
+                    if event.key == Key::Char('v') {
+                        keyboard_event.prevent_default();
+                    }
                     spawn_local(Shell::on_key_combination(keyboard_event, event));
                     return;
                 }
@@ -496,6 +2038,401 @@ impl Shell {
         }
     }
 
+    /// Process terminal data.
+    /// Keys are handled by the custom key event handler, the remaining data is pasted text.
+    fn on_data(data: String) {
+        if Shell::with(|s| s.grid.is_some()) {
+            for event in MouseEvent::parse_reports(&data) {
+                Shell::with_mut(|s| {
+                    let action = match s.grid {
+                        Some(ref mut grid) => grid.on_mouse(&event),
+                        None => return,
+                    };
+                    s.on_grid_action(action);
+                });
+            }
+            return;
+        }
+        if !Shell::with(|s| s.input_enabled) {
+            return;
+        }
+        let text = match data.strip_prefix(vt100::PASTE_BEGIN) {
+            Some(text) => text.strip_suffix(vt100::PASTE_END).unwrap_or(text),
+            None if !data.contains('\x1b') => data.as_str(),
+            None => return,
+        };
+        Shell::on_paste(text);
+    }
+
+    /// Paste a text into the prompt.
+    /// Runs the complete statements first if the prompt is empty and pasted statements should be run.
+    fn on_paste(text: &str) {
+        let text = prompt_buffer::normalize_text(text);
+        let run = Shell::with(|s| {
+            s.settings.paste_run && s.history_search.is_none() && s.input.collect_raw().is_empty()
+        });
+        let (statements, rest) = tokens::split_statements(&text);
+        if !run || statements.is_empty() || text.trim_start().starts_with(&['.', '!'][..]) {
+            Shell::with_mut(|s| {
+                s.input_clock += 1;
+                s.input.paste(&text);
+                s.flush();
+            });
+            Shell::highlight_input();
+            return;
+        }
+        Shell::with_mut(|s| s.block_input());
+        spawn_local(Shell::run_statements(statements, rest));
+    }
+
+    /// Run pasted statements one after another and leave the rest in the prompt.
+    /// Stops at a statement that cannot reach the service and leaves the script from there in the prompt.
+    async fn run_statements(statements: Vec<String>, rest: String) {
+        Shell::with_mut(|s| s.running_script = true);
+        for (i, statement) in statements.iter().enumerate() {
+            Shell::with_mut(|s| {
+                s.block_input();
+                s.input_clock += 1;
+                s.input.replace(statement);
+                s.flush();
+            });
+            Shell::on_sql(statement.clone()).await;
+            let connected = Shell::with(|s| s.service_conn.is_some());
+            if let Some(script) = stopped_script(&statements, i, &rest, connected) {
+                Shell::with_mut(|s| {
+                    s.running_script = false;
+                    s.input_clock += 1;
+                    s.input.replace(&script);
+                    s.flush();
+                });
+                Shell::highlight_input();
+                return;
+            }
+        }
+        Shell::with_mut(|s| s.running_script = false);
+        if !rest.is_empty() {
+            Shell::with_mut(|s| {
+                s.input_clock += 1;
+                s.input.paste(&rest);
+                s.flush();
+            });
+            Shell::highlight_input();
+        }
+    }
+
+    /// Complete the word under the cursor.
+    /// Inserts a tab if there is nothing to complete.
+    fn complete_input() {
+        let highlight = Shell::with_mut(|s| -> bool {
+            // Cycle through the candidates of the previous completion?
+            if let Some(ref mut state) = s.completion {
+                if state.input_clock == s.input_clock {
+                    let next = state
+                        .index
+                        .map(|i| (i + 1) % state.candidates.len())
+                        .unwrap_or(0);
+                    let candidate = &state.candidates[next];
+                    s.input.replace_range(state.begin, state.end, candidate);
+                    state.end = state.begin + candidate.chars().count();
+                    state.index = Some(next);
+                    s.input_clock += 1;
+                    state.input_clock = s.input_clock;
+                    s.flush();
+                    return true;
+                }
+            }
+            s.completion = None;
+            s.input_clock += 1;
+
+            // Complete the word under the cursor, positions ignore the artificial line wraps
+            let text = s.input.collect();
+            let cursor = s.input.logical_cursor();
+            let completion =
+                match completion::complete(&text, cursor, &tokens::tokenize(&text), &s.catalog) {
+                    Some(c) => c,
+                    None => {
+                        s.input.insert_tab();
+                        s.flush();
+                        return false;
+                    }
+                };
+            match completion.candidates.len() {
+                0 => {
+                    s.input.insert_tab();
+                    s.flush();
+                    return false;
+                }
+                1 => {
+                    s.input.replace_range(
+                        completion.begin,
+                        completion.end,
+                        &completion.candidates[0],
+                    );
+                }
+                _ => {
+                    // Extend the word to the common prefix and list the candidates
+                    let common = completion.common_prefix();
+                    let mut end = completion.end;
+                    if common.len() > completion.prefix.len() {
+                        s.input
+                            .replace_range(completion.begin, completion.end, &common);
+                        end = completion.begin + common.chars().count();
+                    }
+                    s.input.print_below(&completion.candidates.join("  "));
+                    s.completion = Some(CompletionState {
+                        begin: completion.begin,
+                        end,
+                        candidates: completion.candidates,
+                        index: None,
+                        input_clock: s.input_clock,
+                    });
+                }
+            }
+            s.flush();
+            true
+        });
+        if highlight {
+            Shell::highlight_input();
+        }
+    }
+
+    /// Process a key event during a history search.
+    /// Returns false if the key should be processed as regular input after ending the search.
+    fn on_search_key(keyboard_event: &web_sys::KeyboardEvent, event: &KeyEvent) -> bool {
+        let ctrl = keyboard_event.ctrl_key() || keyboard_event.meta_key();
+        match event.key {
+            Key::Char('r') if ctrl => Shell::search_history(None),
+            Key::Char('g') if ctrl => Shell::end_history_search(false),
+            Key::Enter | Key::Escape => Shell::end_history_search(true),
+            Key::Backspace => {
+                let mut query = Shell::with(|s| s.history_search.as_ref().unwrap().query.clone());
+                query.pop();
+                Shell::search_history(Some(query));
+            }
+            Key::Char(c) if !ctrl && !c.is_ascii_control() => {
+                let mut query = Shell::with(|s| s.history_search.as_ref().unwrap().query.clone());
+                query.push(c);
+                Shell::search_history(Some(query));
+            }
+            Key::Shift | Key::Alt | Key::Meta | Key::Capslock | Key::Dead => (),
+            _ => {
+                Shell::end_history_search(true);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Search the history backwards for an entry containing the query.
+    /// Continues with the next older match if no new query is provided.
+    fn search_history(query: Option<String>) {
+        Shell::with_mut(|s| {
+            let mut search = s.history_search.take().unwrap_or_else(|| HistorySearch {
+                query: String::new(),
+                match_idx: None,
+                original_input: s.input.collect(),
+            });
+            // Start at the current match for a changed query, one before it for the next match
+            let end = match (&query, search.match_idx) {
+                (None, Some(idx)) => idx,
+                (Some(q), Some(idx)) if q.len() > search.query.len() => idx + 1,
+                _ => s.history.len(),
+            };
+            if let Some(q) = query {
+                search.query = q;
+            }
+            let found = (0..end)
+                .rev()
+                .find(|i| s.history[*i].input.contains(&search.query));
+            if found.is_some() || search.query.is_empty() {
+                search.match_idx = found;
+            }
+            let failed = found.is_none() && !search.query.is_empty();
+            s.input.set_prompt(Some(&format!(
+                "({}reverse-i-search)'{}': ",
+                if failed { "failed " } else { "" },
+                search.query
+            )));
+            let text = match search.match_idx {
+                Some(idx) => s.history[idx].input.clone(),
+                None => search.original_input.clone(),
+            };
+            s.input_clock += 1;
+            s.input.replace(&text);
+            s.history_search = Some(search);
+            s.flush();
+        });
+        Shell::highlight_input();
+    }
+
+    /// End a history search and restore the default prompt.
+    /// Keeps the matching entry if the search is accepted.
+    fn end_history_search(accept: bool) {
+        Shell::with_mut(|s| {
+            let search = match s.history_search.take() {
+                Some(search) => search,
+                None => return,
+            };
+            let text = match (accept, search.match_idx) {
+                (true, Some(idx)) => {
+                    s.history_cursor = idx;
+                    s.history[idx].input.clone()
+                }
+                _ => search.original_input,
+            };
+            s.input.set_prompt(None);
+            s.input_clock += 1;
+            s.input.replace(&text);
+            s.flush();
+        });
+        Shell::highlight_input();
+    }
+
+    /// Process a key event during a search in the terminal output.
+    /// Returns false if the key should be processed as regular input after ending the search.
+    fn on_find_key(keyboard_event: &web_sys::KeyboardEvent, event: &KeyEvent) -> bool {
+        let ctrl = keyboard_event.ctrl_key() || keyboard_event.meta_key();
+        // Edit the query right away so that no typed characters get lost
+        let edit = |f: &dyn Fn(&mut TerminalFind)| {
+            Shell::with_mut(|s| {
+                if let Some(ref mut find) = s.terminal_find {
+                    f(find);
+                }
+            });
+        };
+        match event.key {
+            // Older matches are above, newer matches below
+            Key::Char('f') if ctrl => spawn_local(Shell::find_in_output(false, !event.shift)),
+            Key::Enter => spawn_local(Shell::find_in_output(false, !event.shift)),
+            Key::ArrowUp => spawn_local(Shell::find_in_output(false, true)),
+            Key::ArrowDown => spawn_local(Shell::find_in_output(false, false)),
+            Key::Char('g') if ctrl => Shell::end_find(true),
+            Key::Escape => Shell::end_find(true),
+            Key::Char('c') if event.alt => {
+                edit(&|f| f.options.case_sensitive = !f.options.case_sensitive);
+                spawn_local(Shell::find_in_output(true, true));
+            }
+            Key::Char('r') if event.alt => {
+                edit(&|f| f.options.regex = !f.options.regex);
+                spawn_local(Shell::find_in_output(true, true));
+            }
+            Key::Backspace => {
+                edit(&|f| {
+                    f.query.pop();
+                });
+                spawn_local(Shell::find_in_output(true, true));
+            }
+            Key::Char(c) if !ctrl && !event.alt && !c.is_ascii_control() => {
+                edit(&|f| f.query.push(c));
+                spawn_local(Shell::find_in_output(true, true));
+            }
+            Key::Shift | Key::Alt | Key::Meta | Key::Capslock | Key::Dead => (),
+            _ => {
+                Shell::end_find(false);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Wait until the terminal processed all pending writes
+    async fn sync_terminal() {
+        let promise = Shell::with(|s| {
+            js_sys::Promise::new(&mut |resolve, _| s.terminal.write_callback("", &resolve))
+        });
+        let _ = JsFuture::from(promise).await;
+    }
+
+    /// Get the buffer row of the terminal cursor
+    fn cursor_buffer_row(&self) -> usize {
+        let buffer = self.terminal.get_buffer().get_active();
+        (buffer.get_base_y() + buffer.get_cursor_y()) as usize
+    }
+
+    /// Find the query of the pending search in the terminal output.
+    /// A restarted search begins at the bottom, otherwise the search continues at the selected match.
+    async fn find_in_output(restart: bool, backwards: bool) {
+        Shell::sync_terminal().await;
+        Shell::with_mut(|s| {
+            let addon = s.search_addon.as_ref().map(|a| {
+                AsRef::<JsValue>::as_ref(a)
+                    .clone()
+                    .unchecked_into::<SearchAddon>()
+            });
+            let (addon, mut find) = match (addon, s.terminal_find.take()) {
+                (Some(addon), Some(find)) => (addon, find),
+                _ => return,
+            };
+            let end_row = *find
+                .end_row
+                .get_or_insert_with(|| s.cursor_buffer_row().saturating_sub(s.input.cursor_row()));
+            let valid = !find.query.is_empty()
+                && (!find.options.regex
+                    || terminal_search::compile_regex(&find.query, find.options).is_some());
+            if restart || !valid {
+                s.terminal.clear_selection();
+            }
+
+            // Let the addon select and scroll to the match, matches in the prompt are skipped
+            let mut current = None;
+            if valid {
+                for _ in 0..3 {
+                    let options = SearchOptions::new();
+                    options
+                        .with_regex(find.options.regex)
+                        .with_case_sensitive(find.options.case_sensitive);
+                    let found = if backwards {
+                        addon.find_previous(&find.query, Some(options))
+                    } else {
+                        addon.find_next(&find.query, Some(options))
+                    };
+                    current = s
+                        .terminal
+                        .get_selection_position()
+                        .filter(|_| found)
+                        .map(|p| (p.start_row() as usize, p.start_column() as usize));
+                    match current {
+                        Some((row, _)) if row >= end_row => current = None,
+                        _ => break,
+                    }
+                }
+                if current.is_none() {
+                    s.terminal.clear_selection();
+                }
+            }
+
+            // Count the matches and locate the selected one
+            let matches = if valid {
+                let buffer = s.terminal.get_buffer().get_active();
+                let lines = terminal_search::read_lines(&buffer, end_row);
+                terminal_search::find_matches(&lines, s.terminal_width, &find.query, find.options)
+            } else {
+                Vec::new()
+            };
+            let index = current.and_then(|c| matches.iter().position(|m| *m == c));
+            s.input.set_prompt(Some(&find.prompt(index, matches.len())));
+            s.input.refresh();
+            s.terminal_find = Some(find);
+            s.flush();
+        });
+    }
+
+    /// End a search in the terminal output and scroll back to the prompt
+    fn end_find(clear_selection: bool) {
+        Shell::with_mut(|s| {
+            if s.terminal_find.take().is_none() {
+                return;
+            }
+            if clear_selection {
+                s.terminal.clear_selection();
+            }
+            s.input.set_prompt(None);
+            s.input.refresh();
+            s.terminal.scroll_to_bottom();
+            s.flush();
+        });
+    }
+
     /// Handle pressed key combinations such as ctrl+c & ctrl+v
     async fn on_key_combination(keyboard_event: web_sys::KeyboardEvent, event: KeyEvent) {
         let rt_ptr = Shell::with_mut(|s| s.runtime.clone()).unwrap();
@@ -503,30 +2440,38 @@ impl Shell {
         if keyboard_event.ctrl_key() || keyboard_event.meta_key() {
             match event.key {
                 Key::Char('v') => match rt.read_clipboard_text().await {
-                    Ok(v) => {
-                        Shell::with_mut(|s| {
-                            s.input
-                                .insert_text(&v.as_string().unwrap_or_else(|| ' '.to_string()));
-                            s.input.flush(&s.terminal);
-                        });
-                        Shell::highlight_input();
-                    }
+                    Ok(v) => Shell::on_paste(&v.as_string().unwrap_or_default()),
                     Err(e) => warn!("Failed to read from clipboard: {:?}", e.to_string()),
                 },
-                Key::Char('a') => {
+                Key::Char('c') => (),
+                Key::Char('r') => Shell::search_history(None),
+                Key::Char('f') => {
+                    Shell::with_mut(|s| {
+                        s.terminal_find = Some(TerminalFind {
+                            query: String::new(),
+                            options: FindOptions::default(),
+                            end_row: None,
+                        })
+                    });
+                    Shell::find_in_output(true, true).await;
+                }
+                Key::Char('l') => {
                     Shell::with_mut(|s| {
-                        s.input.move_cursor_to(0);
-                        s.input.flush(&s.terminal);
+                        s.write(&format!("{}{}", vt100::CLEAR_SCREEN, vt100::CURSOR_HOME));
+                        s.input.redraw();
+                        s.flush();
                     });
+                    Shell::highlight_input();
                 }
-                Key::Char('e') => {
+                // Let the prompt buffer handle the editing keys
+                _ => {
                     Shell::with_mut(|s| {
-                        s.input.move_cursor_to_end();
-                        s.input.flush(&s.terminal);
+                        s.input_clock += 1;
+                        s.input.consume(event);
+                        s.flush();
                     });
+                    Shell::highlight_input();
                 }
-                Key::Char('c') => (),
-                _ => {}
             }
         }
     }
@@ -583,3 +2528,72 @@ impl Shell {
         SHELL.with(|s| f(&mut s.borrow_mut()))
     }
 }
+
+/// Get the current time for file names
+fn file_timestamp() -> String {
+    pretty_timestamp(now_timestamp())
+        .replace(' ', "-")
+        .replace(':', "")
+}
+
+/// Get the delay before a reconnect attempt in milliseconds
+fn reconnect_delay(attempt: u32) -> i32 {
+    RECONNECT_DELAY_MIN
+        .saturating_mul(2_i32.saturating_pow(attempt))
+        .min(RECONNECT_DELAY_MAX)
+}
+
+/// Get the script that stays in the prompt if pasted statements stop after a statement.
+/// The script stops if the statement could not reach the service, it continues otherwise.
+fn stopped_script(
+    statements: &[String],
+    index: usize,
+    rest: &str,
+    connected: bool,
+) -> Option<String> {
+    if connected {
+        return None;
+    }
+    let mut script = statements[index..].join("\n");
+    if !rest.is_empty() {
+        script.push('\n');
+        script.push_str(rest);
+    }
+    Some(script)
+}
+
+/// Is a statement changing the catalog?
+fn is_ddl(text: &str) -> bool {
+    let keyword: String = text
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphabetic())
+        .collect();
+    matches!(
+        keyword.to_ascii_uppercase().as_str(),
+        "CREATE" | "DROP" | "ALTER"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopped_script() {
+        let statements = vec![
+            "select 1;".to_string(),
+            "select 2;".to_string(),
+            "select 3;".to_string(),
+        ];
+        assert_eq!(stopped_script(&statements, 0, "select", true), None);
+        assert_eq!(
+            stopped_script(&statements, 1, "select", false),
+            Some("select 2;\nselect 3;\nselect".to_string())
+        );
+        assert_eq!(
+            stopped_script(&statements, 2, "", false),
+            Some("select 3;".to_string())
+        );
+    }
+}
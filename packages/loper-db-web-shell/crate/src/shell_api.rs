@@ -2,10 +2,13 @@ use crate::shell;
 use crate::shell::Shell;
 use crate::shell_options;
 use crate::shell_runtime;
+use crate::shell_runtime::{ConnectionProfile, HistoryEntry};
 use crate::xterm::addons::fit::FitAddon;
+use crate::xterm::addons::unicode11::Unicode11Addon;
 use crate::xterm::addons::web_links::WebLinksAddon;
 use crate::xterm::addons::webgl::WebglAddon;
 use crate::xterm::{Terminal, TerminalOptions, Theme};
+use log::warn;
 use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -49,6 +52,11 @@ pub fn embed(
     let fit_addon = FIT_ADDON.with(|a| a.borrow().clone());
     terminal.load_addon(fit_addon.into());
     terminal.load_addon(links_addon.clone().dyn_into::<WebLinksAddon>()?.into());
+
+    // Use the unicode 11 widths that the prompt buffer computes as well
+    let unicode_addon = Unicode11Addon::new();
+    terminal.load_addon(unicode_addon.dyn_into::<Unicode11Addon>()?.into());
+    terminal.get_unicode().set_active_version("11");
     FIT_ADDON.with(|a| a.borrow().fit());
 
     Shell::with_mut(|s| s.attach(terminal, runtime, options));
@@ -72,8 +80,32 @@ pub fn resize(_width: usize, _height: usize) {
 
 #[wasm_bindgen(js_name = "loadHistory")]
 pub fn load_history(history: &js_sys::Array, cursor: usize) {
-    let h: Vec<String> = history.iter().map(|ref v| v.as_string().unwrap()).collect();
-    Shell::load_history(h, cursor);
+    let mut h: Vec<HistoryEntry> = Vec::with_capacity(history.length() as usize);
+    let mut h_cursor = 0;
+    for (i, v) in history.iter().enumerate() {
+        let entry = js_sys::JSON::stringify(&v)
+            .ok()
+            .and_then(|json| serde_json::from_str(&String::from(json)).ok());
+        match entry {
+            Some(entry) => h.push(entry),
+            None => warn!("Dropped invalid history entry {}", i),
+        }
+        // The cursor moves with the entries before it
+        if i + 1 == cursor {
+            h_cursor = h.len();
+        }
+    }
+    Shell::load_history(h, h_cursor);
+}
+
+#[wasm_bindgen(js_name = "loadProfiles")]
+pub fn load_profiles(profiles: &js_sys::Array) {
+    let p: Vec<ConnectionProfile> = profiles
+        .iter()
+        .filter_map(|ref v| js_sys::JSON::stringify(v).ok())
+        .filter_map(|json| serde_json::from_str(&String::from(json)).ok())
+        .collect();
+    Shell::load_profiles(p);
 }
 
 #[wasm_bindgen(js_name = "initialSetup")]
@@ -1,4 +1,6 @@
+use crate::loper::JsLoperServiceClient;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -19,13 +21,133 @@ impl FileInfo {
     }
 }
 
+/// A history entry with the outcome of the input
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct HistoryEntry {
+    /// The input text
+    pub input: String,
+    /// The time of the input in milliseconds since the epoch
+    #[serde(default)]
+    pub timestamp: f64,
+    /// The elapsed time in milliseconds
+    #[serde(default)]
+    pub elapsed: Option<f64>,
+    /// The number of result rows
+    #[serde(default)]
+    pub rows: Option<u64>,
+    /// Did the input succeed?
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// The error message (if any)
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The service url
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn from_input(input: &str, timestamp: f64, url: &str) -> Self {
+        Self {
+            input: input.to_string(),
+            timestamp,
+            url: Some(url.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A named connection profile
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionProfile {
+    /// The profile name
+    pub name: String,
+    /// The service url
+    pub url: String,
+    /// The metadata that is sent with every request, e.g. auth tokens
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Split command arguments at whitespace, quotes keep whitespace, e.g. key="Bearer abc".
+/// Returns None if a quote is not closed.
+fn split_arguments(args: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_part = false;
+    let mut quote = None;
+    for c in args.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_part = true;
+            }
+            None if c.is_whitespace() => {
+                if in_part {
+                    parts.push(std::mem::take(&mut current));
+                    in_part = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_part = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_part {
+        parts.push(current);
+    }
+    Some(parts)
+}
+
+impl ConnectionProfile {
+    /// Parse a profile from command arguments: <name> <url> [key=value ...]
+    pub fn parse(args: &str) -> Option<Self> {
+        let mut parts = split_arguments(args)?.into_iter();
+        let name = parts.next()?;
+        let url = parts.next()?;
+        let mut metadata = BTreeMap::new();
+        for part in parts {
+            let (key, value) = part.split_once('=')?;
+            if key.is_empty() {
+                return None;
+            }
+            metadata.insert(key.to_lowercase(), value.to_string());
+        }
+        Some(Self {
+            name,
+            url,
+            metadata,
+        })
+    }
+
+    /// Convert the metadata to a javascript object
+    pub fn metadata_value(&self) -> JsValue {
+        serde_json::to_string(&self.metadata)
+            .ok()
+            .and_then(|json| js_sys::JSON::parse(&json).ok())
+            .unwrap_or(JsValue::NULL)
+    }
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_name = "ShellRuntime")]
     pub type ShellRuntime;
 
     #[wasm_bindgen(method, catch, js_name = "configureClient")]
-    pub async fn configure_client(this: &ShellRuntime, url: &str) -> Result<JsValue, js_sys::Error>;
+    pub async fn configure_client(
+        this: &ShellRuntime,
+        url: &str,
+        metadata: JsValue,
+    ) -> Result<JsValue, js_sys::Error>;
+    #[wasm_bindgen(method, js_name = "useClient")]
+    pub fn use_client(this: &ShellRuntime, client: JsLoperServiceClient);
     #[wasm_bindgen(method, catch, js_name = "readClipboardText")]
     pub async fn read_clipboard_text(this: &ShellRuntime) -> Result<JsValue, js_sys::Error>;
     #[wasm_bindgen(method, catch, js_name = "writeClipboardText")]
@@ -33,9 +155,47 @@ extern "C" {
         this: &ShellRuntime,
         value: &str,
     ) -> Result<(), js_sys::Error>;
-    #[wasm_bindgen(method, catch, js_name = "pushInputToHistory")]
-    pub async fn push_input_to_history(
+    #[wasm_bindgen(method, catch, js_name = "pushHistoryEntry")]
+    pub async fn push_history_entry(
         this: &ShellRuntime,
-        value: &str,
+        entry: JsValue,
+    ) -> Result<(), js_sys::Error>;
+    #[wasm_bindgen(method, catch, js_name = "downloadFile")]
+    pub async fn download_file(
+        this: &ShellRuntime,
+        name: &str,
+        content: &str,
+        mime_type: &str,
     ) -> Result<(), js_sys::Error>;
+    #[wasm_bindgen(method, catch, js_name = "saveProfiles")]
+    pub async fn save_profiles(this: &ShellRuntime, profiles: JsValue)
+        -> Result<(), js_sys::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = ConnectionProfile::parse(
+            "prod https://db.example.com Authorization=\"Bearer eyJhbGciOi.J9\" 'x-tenant=7'",
+        )
+        .unwrap();
+        assert_eq!(profile.name, "prod");
+        assert_eq!(profile.url, "https://db.example.com");
+        assert_eq!(profile.metadata.len(), 2);
+        assert_eq!(profile.metadata["authorization"], "Bearer eyJhbGciOi.J9");
+        assert_eq!(profile.metadata["x-tenant"], "7");
+        assert!(
+            ConnectionProfile::parse("prod https://db.example.com auth=\"Bearer abc").is_none()
+        );
+        assert!(ConnectionProfile::parse("local http://localhost:8080")
+            .unwrap()
+            .metadata
+            .is_empty());
+        assert!(ConnectionProfile::parse("prod").is_none());
+        assert!(ConnectionProfile::parse("prod https://db.example.com token").is_none());
+        assert!(ConnectionProfile::parse("prod https://db.example.com =abc").is_none());
+    }
 }
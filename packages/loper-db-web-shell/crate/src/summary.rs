@@ -0,0 +1,336 @@
+use crate::arrow_format::{format_full_value, format_value, is_numeric, FormatOptions};
+use arrow::array::{Array, ArrayRef, Float64Array, StringArray, UInt64Array};
+use arrow::compute::{cast, concat, sort_to_indices, sum, SortOptions};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::{ArrowError, Result};
+use arrow::record_batch::RecordBatch;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// The number of most frequent values shown per column
+pub const TOP_VALUES: usize = 3;
+/// The number of distinct values that are counted for the top values
+const MAX_TRACKED_VALUES: usize = 1000;
+/// The maximum terminal columns of a top value
+const TOP_VALUE_WIDTH: usize = 20;
+/// The number of hash bits that select a register of the distinct counter
+const DISTINCT_PRECISION: u32 = 10;
+
+/// Estimates the number of distinct values with HyperLogLog
+struct DistinctCounter {
+    /// The maximum rank of the hashes per register
+    registers: Vec<u8>,
+}
+
+impl DistinctCounter {
+    /// Construct an empty counter
+    fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << DISTINCT_PRECISION],
+        }
+    }
+
+    /// Add a value
+    fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - DISTINCT_PRECISION)) as usize;
+        // The position of the first one bit after the register bits, a sentinel bit limits the rank
+        let rank =
+            ((hash << DISTINCT_PRECISION) | (1 << (DISTINCT_PRECISION - 1))).leading_zeros() + 1;
+        self.registers[register] = std::cmp::max(self.registers[register], rank as u8);
+    }
+
+    /// Estimate the number of distinct values
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|r| 2_f64.powi(-(*r as i32)))
+            .sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        // Count the empty registers for small cardinalities
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            return (m * (m / zeros as f64).ln()).round() as u64;
+        }
+        estimate.round() as u64
+    }
+}
+
+/// Get the query of a statement starting with SUMMARIZE, e.g. SUMMARIZE SELECT 1
+pub fn strip_summarize(text: &str) -> Option<&str> {
+    let text = text.trim_start();
+    let keyword = text.get(..9)?;
+    let query = &text[9..];
+    if !keyword.eq_ignore_ascii_case("summarize") || !query.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(query.trim_start())
+}
+
+/// The statistics of a column
+struct ColumnSummary {
+    /// The number of values that are not null
+    count: u64,
+    /// The number of nulls
+    nulls: u64,
+    /// The smallest value (if the type is sortable)
+    min: Option<String>,
+    /// The largest value (if the type is sortable)
+    max: Option<String>,
+    /// The mean (if the type is numeric)
+    mean: Option<f64>,
+    /// The approximate number of distinct values
+    distinct: u64,
+    /// The most frequent values with their counts, e.g. a (3), b (2)
+    top: Option<String>,
+}
+
+/// Compute the statistics of a column
+fn summarize_column(column: &ArrayRef, options: &FormatOptions) -> Result<ColumnSummary> {
+    let nulls = column.null_count();
+    let count = column.len() - nulls;
+
+    // Sort to find the smallest and the largest value, some types like structs cannot be sorted
+    let sort_options = SortOptions {
+        descending: false,
+        nulls_first: false,
+    };
+    let (min, max) = match sort_to_indices(column, Some(sort_options), None) {
+        Ok(indices) if count > 0 => (
+            Some(format_value(column, indices.value(0) as usize, options)?),
+            Some(format_value(
+                column,
+                indices.value(count - 1) as usize,
+                options,
+            )?),
+        ),
+        _ => (None, None),
+    };
+
+    let mean = if is_numeric(column.data_type()) && count > 0 {
+        let floats = cast(column, &DataType::Float64)?;
+        floats
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .and_then(sum)
+            .map(|sum| sum / count as f64)
+    } else {
+        None
+    };
+
+    // Count the values by their full text, new values are ignored once enough are tracked
+    let mut distinct = DistinctCounter::new();
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for row in 0..column.len() {
+        if column.is_null(row) {
+            continue;
+        }
+        let value = format_full_value(column, row, options)?;
+        distinct.insert(&value);
+        if let Some((n, _)) = counts.get_mut(&value) {
+            *n += 1;
+        } else if counts.len() < MAX_TRACKED_VALUES {
+            counts.insert(value, (1, row));
+        }
+    }
+    let mut top: Vec<(usize, usize)> = counts.into_values().filter(|(n, _)| *n > 1).collect();
+    top.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let top_options = FormatOptions {
+        max_width: Some(TOP_VALUE_WIDTH),
+        ..options.clone()
+    };
+    let top = top
+        .iter()
+        .take(TOP_VALUES)
+        .map(|(n, row)| {
+            Ok(format!(
+                "{} ({})",
+                format_value(column, *row, &top_options)?,
+                n
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ColumnSummary {
+        count: count as u64,
+        nulls: nulls as u64,
+        min,
+        max,
+        mean,
+        distinct: distinct.estimate(),
+        top: if top.is_empty() {
+            None
+        } else {
+            Some(top.join(", "))
+        },
+    })
+}
+
+/// Compute the statistics of every column of the record batches of a result.
+/// Returns a record batch with one row per column.
+pub fn summarize(batches: &[RecordBatch], options: &FormatOptions) -> Result<RecordBatch> {
+    let schema = match batches.first() {
+        Some(batch) => batch.schema(),
+        None => {
+            return Err(ArrowError::InvalidArgumentError(
+                "the result has no columns".to_string(),
+            ))
+        }
+    };
+    let mut summaries = Vec::with_capacity(schema.fields().len());
+    for i in 0..schema.fields().len() {
+        let column = if batches.len() == 1 {
+            batches[0].column(i).clone()
+        } else {
+            let arrays: Vec<&dyn Array> = batches.iter().map(|b| b.column(i).as_ref()).collect();
+            concat(&arrays)?
+        };
+        summaries.push(summarize_column(&column, options)?);
+    }
+
+    let fields = schema.fields();
+    let summary_schema = Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("count", DataType::UInt64, false),
+        Field::new("nulls", DataType::UInt64, false),
+        Field::new("min", DataType::Utf8, true),
+        Field::new("max", DataType::Utf8, true),
+        Field::new("mean", DataType::Float64, true),
+        Field::new("distinct", DataType::UInt64, false),
+        Field::new("top", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.name()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.data_type().to_string()),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            summaries.iter().map(|s| s.count),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            summaries.iter().map(|s| s.nulls),
+        )),
+        Arc::new(
+            summaries
+                .iter()
+                .map(|s| s.min.clone())
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            summaries
+                .iter()
+                .map(|s| s.max.clone())
+                .collect::<StringArray>(),
+        ),
+        Arc::new(summaries.iter().map(|s| s.mean).collect::<Float64Array>()),
+        Arc::new(UInt64Array::from_iter_values(
+            summaries.iter().map(|s| s.distinct),
+        )),
+        Arc::new(
+            summaries
+                .iter()
+                .map(|s| s.top.clone())
+                .collect::<StringArray>(),
+        ),
+    ];
+    RecordBatch::try_new(Arc::new(summary_schema), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StructArray};
+    use arrow::util::display::array_value_to_string;
+
+    fn batch(ids: Vec<Option<i32>>, names: Vec<Option<&str>>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(StringArray::from(names)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn cell(batch: &RecordBatch, column: &str, row: usize) -> String {
+        let index = batch.schema().index_of(column).unwrap();
+        array_value_to_string(batch.column(index), row).unwrap()
+    }
+
+    #[test]
+    fn test_summarize() -> Result<()> {
+        let batches = vec![
+            batch(
+                vec![Some(3), None, Some(1)],
+                vec![Some("b"), Some("a"), Some("b")],
+            ),
+            batch(vec![Some(4), Some(4)], vec![Some("c"), None]),
+        ];
+        let summary = summarize(&batches, &FormatOptions::default())?;
+        assert_eq!(summary.num_rows(), 2);
+        assert_eq!(cell(&summary, "column", 0), "id");
+        assert_eq!(cell(&summary, "type", 0), "Int32");
+        assert_eq!(cell(&summary, "count", 0), "4");
+        assert_eq!(cell(&summary, "nulls", 0), "1");
+        assert_eq!(cell(&summary, "min", 0), "1");
+        assert_eq!(cell(&summary, "max", 0), "4");
+        assert_eq!(cell(&summary, "mean", 0), "3");
+        assert_eq!(cell(&summary, "distinct", 0), "3");
+        assert_eq!(cell(&summary, "top", 0), "4 (2)");
+
+        assert_eq!(cell(&summary, "column", 1), "name");
+        assert_eq!(cell(&summary, "count", 1), "4");
+        assert_eq!(cell(&summary, "min", 1), "a");
+        assert_eq!(cell(&summary, "max", 1), "c");
+        assert!(summary.column(6).is_null(1));
+        assert_eq!(cell(&summary, "distinct", 1), "3");
+        assert_eq!(cell(&summary, "top", 1), "b (2)");
+
+        // Structs have no order
+        let ids: ArrayRef = Arc::new(Int32Array::from(vec![1, 1]));
+        let pairs: ArrayRef = Arc::new(StructArray::from(vec![(
+            Field::new("id", DataType::Int32, false),
+            ids,
+        )]));
+        let pairs = RecordBatch::try_from_iter(vec![("pair", pairs)])?;
+        let summary = summarize(&[pairs], &FormatOptions::default())?;
+        assert!(summary.column(4).is_null(0));
+        assert_eq!(cell(&summary, "distinct", 0), "1");
+        assert_eq!(cell(&summary, "top", 0), "{id: 1} (2)");
+        assert!(summarize(&[], &FormatOptions::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_estimate() {
+        let mut counter = DistinctCounter::new();
+        for i in 0..100_000 {
+            counter.insert(&(i % 20_000).to_string());
+        }
+        let estimate = counter.estimate() as f64;
+        assert!((estimate - 20_000.0).abs() / 20_000.0 < 0.1, "{}", estimate);
+    }
+
+    #[test]
+    fn test_strip_summarize() {
+        assert_eq!(strip_summarize("SUMMARIZE SELECT 1"), Some("SELECT 1"));
+        assert_eq!(strip_summarize("  summarize\nselect 1"), Some("select 1"));
+        assert_eq!(strip_summarize("SELECT summarize FROM t"), None);
+        assert_eq!(strip_summarize("summarized"), None);
+        assert_eq!(strip_summarize("SUMMARIZE"), None);
+    }
+}
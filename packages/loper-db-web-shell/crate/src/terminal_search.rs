@@ -0,0 +1,180 @@
+use crate::xterm::Buffer;
+use unicode_width::UnicodeWidthChar;
+use wasm_bindgen::JsCast;
+
+/// A line of the terminal buffer, joined from its wrapped rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextLine {
+    /// The first row of the line in the buffer
+    pub row: usize,
+    /// The text of the line
+    pub text: String,
+}
+
+/// The options of a search in the terminal output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Match the case of the query
+    pub case_sensitive: bool,
+    /// Interpret the query as regular expression
+    pub regex: bool,
+}
+
+/// Read the lines of the terminal buffer before a row
+pub fn read_lines(buffer: &Buffer, end_row: usize) -> Vec<TextLine> {
+    let end_row = std::cmp::min(end_row, buffer.get_length() as usize);
+    let mut lines: Vec<TextLine> = Vec::new();
+    for row in 0..end_row {
+        let line = buffer.get_line(row as u32);
+        let wrapped = line.is_wrapped();
+        // Only the last row of a wrapped line is trimmed, the others fill the terminal width
+        let next_wrapped = row + 1 < end_row && buffer.get_line(row as u32 + 1).is_wrapped();
+        let text = line.translate_to_String(!next_wrapped, None, None);
+        match lines.last_mut() {
+            Some(last) if wrapped => last.text.push_str(&text),
+            _ => lines.push(TextLine { row, text }),
+        }
+    }
+    lines
+}
+
+/// Compile the query as javascript regular expression.
+/// Returns None if the query is not a valid expression.
+pub fn compile_regex(query: &str, options: FindOptions) -> Option<js_sys::RegExp> {
+    let flags = if options.case_sensitive { "g" } else { "gi" };
+    let constructor = js_sys::RegExp::new("", "").constructor();
+    let args = js_sys::Array::of2(&query.into(), &flags.into());
+    js_sys::Reflect::construct(&constructor, &args)
+        .ok()
+        .map(|r| r.unchecked_into())
+}
+
+/// Find the character offsets of the regular expression matches in a text
+fn regex_offsets(text: &str, regex: &js_sys::RegExp) -> Vec<usize> {
+    // The match indices are UTF-16 offsets
+    let mut utf16_offsets = Vec::new();
+    regex.set_last_index(0);
+    while let Some(m) = regex.exec(text) {
+        let index = js_sys::Reflect::get(&m, &"index".into())
+            .ok()
+            .and_then(|i| i.as_f64())
+            .unwrap_or(0.0) as u32;
+        utf16_offsets.push(index as usize);
+        // Skip empty matches
+        if regex.last_index() == index {
+            regex.set_last_index(index + 1);
+        }
+    }
+    let mut offsets = Vec::with_capacity(utf16_offsets.len());
+    let mut utf16 = 0;
+    let mut pending = utf16_offsets.into_iter().peekable();
+    for (i, c) in text.chars().enumerate() {
+        while pending.peek().map(|o| *o <= utf16).unwrap_or(false) {
+            pending.next();
+            offsets.push(i);
+        }
+        utf16 += c.len_utf16();
+    }
+    offsets
+}
+
+/// Find the character offsets of the plain text matches in a text
+pub fn text_offsets(text: &str, query: &str, case_sensitive: bool) -> Vec<usize> {
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let text: Vec<char> = text.chars().map(fold).collect();
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let mut offsets = Vec::new();
+    if query.is_empty() {
+        return offsets;
+    }
+    let mut i = 0;
+    while i + query.len() <= text.len() {
+        if text[i..i + query.len()] == query[..] {
+            offsets.push(i);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    offsets
+}
+
+/// Find the matches of a query in the lines.
+/// Returns the rows and columns of the matches in the terminal buffer.
+pub fn find_matches(
+    lines: &[TextLine],
+    cols: usize,
+    query: &str,
+    options: FindOptions,
+) -> Vec<(usize, usize)> {
+    let regex = if options.regex {
+        match compile_regex(query, options) {
+            Some(regex) => Some(regex),
+            None => return Vec::new(),
+        }
+    } else {
+        None
+    };
+    let mut matches = Vec::new();
+    for line in lines.iter() {
+        let offsets = match regex {
+            Some(ref regex) => regex_offsets(&line.text, regex),
+            None => text_offsets(&line.text, query, options.case_sensitive),
+        };
+        // Translate the character offsets to terminal cells
+        let mut chars = line.text.chars();
+        let (mut offset, mut cell) = (0, 0);
+        for o in offsets {
+            for c in chars.by_ref().take(o - offset) {
+                cell += c.width().unwrap_or(0);
+            }
+            offset = o;
+            let cols = std::cmp::max(cols, 1);
+            matches.push((line.row + cell / cols, cell % cols));
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(row: usize, text: &str) -> TextLine {
+        TextLine {
+            row,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let lines = vec![
+            line(0, "loper> select 'Foo', 'foo';"),
+            line(2, "│ Foo │ foo │"),
+            line(3, "│ 田中 │ foofoo │"),
+            line(4, "a long line that wraps at the end with foo"),
+        ];
+        let options = FindOptions::default();
+        assert_eq!(
+            find_matches(&lines, 20, "foo", options),
+            vec![(0, 15), (1, 2), (2, 2), (2, 8), (3, 9), (3, 12), (5, 19)]
+        );
+        let case_sensitive = FindOptions {
+            case_sensitive: true,
+            ..options
+        };
+        assert_eq!(
+            find_matches(&lines, 20, "Foo", case_sensitive),
+            vec![(0, 15), (2, 2)]
+        );
+        assert!(find_matches(&lines, 20, "", options).is_empty());
+        assert!(find_matches(&lines, 20, "bar", options).is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::Write;
+
+/// The xterm colors of the 16 color palette
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// The format of a saved transcript
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    /// Plain text
+    Text,
+    /// A HTML document with the colors of the terminal
+    Html,
+    /// The raw escape sequences that restore the terminal
+    Ansi,
+}
+
+impl TranscriptFormat {
+    /// Parse a format argument, plain text is the default
+    pub fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "" | "txt" => Some(TranscriptFormat::Text),
+            "html" => Some(TranscriptFormat::Html),
+            "ansi" => Some(TranscriptFormat::Ansi),
+            _ => None,
+        }
+    }
+
+    /// Get the file extension
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscriptFormat::Text => "txt",
+            TranscriptFormat::Html => "html",
+            TranscriptFormat::Ansi => "ansi",
+        }
+    }
+
+    /// Get the mime type
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            TranscriptFormat::Html => "text/html",
+            TranscriptFormat::Text | TranscriptFormat::Ansi => "text/plain",
+        }
+    }
+}
+
+/// An escape sequence or a character of a text
+enum Segment<'a> {
+    /// A printable character
+    Char(char),
+    /// The parameters of a select graphic rendition sequence
+    Sgr(&'a str),
+    /// Any other escape sequence
+    Other,
+}
+
+/// Split a text into characters and escape sequences
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut iter = text.char_indices().peekable();
+    while let Some((i, c)) = iter.next() {
+        if c != '\x1b' {
+            segments.push(Segment::Char(c));
+            continue;
+        }
+        match iter.next() {
+            // Control sequence, ends with a byte in @..~
+            Some((_, '[')) => {
+                let begin = i + 2;
+                let mut end = text.len();
+                let mut last = None;
+                for (j, c) in iter.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        end = j;
+                        last = Some(c);
+                        break;
+                    }
+                }
+                segments.push(match last {
+                    Some('m') => Segment::Sgr(&text[begin..end]),
+                    _ => Segment::Other,
+                });
+            }
+            // Operating system command, ends with BEL or ST
+            Some((_, ']')) => {
+                while let Some((_, c)) = iter.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && iter.peek().map(|(_, c)| *c == '\\').unwrap_or(false) {
+                        iter.next();
+                        break;
+                    }
+                }
+                segments.push(Segment::Other);
+            }
+            _ => segments.push(Segment::Other),
+        }
+    }
+    segments
+}
+
+/// Remove the escape sequences and carriage returns from a text
+pub fn strip_ansi(text: &str) -> String {
+    segments(text)
+        .into_iter()
+        .filter_map(|s| match s {
+            Segment::Char('\r') => None,
+            Segment::Char(c) => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the color of the 256 color palette
+fn palette_color(n: u32) -> String {
+    match n {
+        0..=15 => PALETTE[n as usize].to_string(),
+        16..=231 => {
+            let level = |v: u32| if v == 0 { 0 } else { v * 40 + 55 };
+            let n = n - 16;
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(n / 36),
+                level((n / 6) % 6),
+                level(n % 6)
+            )
+        }
+        _ => {
+            let v = 8 + (n.min(255) - 232) * 10;
+            format!("#{:02x}{:02x}{:02x}", v, v, v)
+        }
+    }
+}
+
+/// The text attributes of a select graphic rendition
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Attributes {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    foreground: Option<String>,
+    background: Option<String>,
+}
+
+impl Attributes {
+    /// Apply the parameters of a select graphic rendition
+    fn apply(&mut self, params: &str) {
+        let params: Vec<u32> = params
+            .split([';', ':'])
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = Attributes::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                n @ 30..=37 => self.foreground = Some(palette_color(n - 30)),
+                n @ 40..=47 => self.background = Some(palette_color(n - 40)),
+                n @ 90..=97 => self.foreground = Some(palette_color(n - 90 + 8)),
+                n @ 100..=107 => self.background = Some(palette_color(n - 100 + 8)),
+                39 => self.foreground = None,
+                49 => self.background = None,
+                n @ 38 | n @ 48 => {
+                    let color = match params.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            params.get(i).map(|c| palette_color(*c))
+                        }
+                        Some(2) => {
+                            i += 4;
+                            params
+                                .get(i - 2..=i)
+                                .map(|rgb| format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]))
+                        }
+                        _ => None,
+                    };
+                    if n == 38 {
+                        self.foreground = color;
+                    } else {
+                        self.background = color;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Get the css style of the attributes
+    fn style(&self) -> String {
+        let mut style = String::new();
+        if self.bold {
+            style.push_str("font-weight:bold;");
+        }
+        if self.italic {
+            style.push_str("font-style:italic;");
+        }
+        if self.underline {
+            style.push_str("text-decoration:underline;");
+        }
+        if let Some(ref color) = self.foreground {
+            write!(style, "color:{};", color).unwrap();
+        }
+        if let Some(ref color) = self.background {
+            write!(style, "background-color:{};", color).unwrap();
+        }
+        style
+    }
+}
+
+/// Convert a text with escape sequences to a HTML document
+pub fn ansi_to_html(text: &str, title: &str) -> String {
+    let mut body = String::new();
+    let mut attributes = Attributes::default();
+    let mut span_open = false;
+    for segment in segments(text) {
+        match segment {
+            Segment::Sgr(params) => {
+                let mut next = attributes.clone();
+                next.apply(params);
+                if next == attributes {
+                    continue;
+                }
+                if span_open {
+                    body.push_str("</span>");
+                }
+                let style = next.style();
+                span_open = !style.is_empty();
+                if span_open {
+                    write!(body, "<span style=\"{}\">", style).unwrap();
+                }
+                attributes = next;
+            }
+            Segment::Char('\r') | Segment::Other => {}
+            Segment::Char('&') => body.push_str("&amp;"),
+            Segment::Char('<') => body.push_str("&lt;"),
+            Segment::Char('>') => body.push_str("&gt;"),
+            Segment::Char(c) => body.push(c),
+        }
+    }
+    if span_open {
+        body.push_str("</span>");
+    }
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>{title}</title>\n",
+            "</head>\n",
+            "<body style=\"background-color:#ffffff;color:#000000\">\n",
+            "<pre style=\"font-family:monospace\">{body}</pre>\n",
+            "</body>\n",
+            "</html>\n"
+        ),
+        title = title.replace('&', "&amp;").replace('<', "&lt;"),
+        body = body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi(
+                "\x1b[1mloper\x1b[m> select 1;\r\n\x1b[31mERROR\x1b[0m: x\x1b[2K\x1b]0;title\x07"
+            ),
+            "loper> select 1;\nERROR: x"
+        );
+        assert_eq!(strip_ansi("田中 \x1b[38;5;12mok"), "田中 ok");
+    }
+
+    #[test]
+    fn test_ansi_to_html() {
+        let html = ansi_to_html(
+            "\x1b[1;31mERROR\x1b[0m: a < b & c\r\n\x1b[38;2;0;128;255mblue\x1b[39m",
+            "t",
+        );
+        assert!(html.contains(
+            "<span style=\"font-weight:bold;color:#cd0000;\">ERROR</span>: a &lt; b &amp; c\n"
+        ));
+        assert!(html.contains("<span style=\"color:#0080ff;\">blue</span></pre>"));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(palette_color(196), "#ff0000");
+        assert_eq!(palette_color(244), "#808080");
+    }
+}
@@ -1,5 +1,8 @@
-use chrono::Duration;
+use chrono::{Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use std::cell::RefCell;
 use std::cmp;
+use std::collections::HashMap;
+use wasm_bindgen::JsCast;
 use web_sys;
 
 pub fn now() -> f64 {
@@ -10,6 +13,35 @@ pub fn now() -> f64 {
         .now()
 }
 
+/// Wait for a number of milliseconds
+pub async fn sleep(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .expect("should have a Window")
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+            .expect("should register a timeout");
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+pub fn now_timestamp() -> f64 {
+    js_sys::Date::now()
+}
+
+pub fn pretty_timestamp(ms: f64) -> String {
+    let offset = js_sys::Date::new(&ms.into()).get_timezone_offset() as i32;
+    let utc = NaiveDateTime::from_timestamp_opt(
+        (ms / 1000.0).floor() as i64,
+        ((ms % 1000.0) * 1_000_000.0) as u32,
+    )
+    .unwrap_or_default();
+    FixedOffset::west_opt(offset * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+        .from_utc_datetime(&utc)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
 pub fn pretty_elapsed(d: &Duration) -> String {
     if d.num_seconds() == 0 {
         return format!("{} ms", d.num_milliseconds());
@@ -38,6 +70,99 @@ pub fn pretty_elapsed(d: &Duration) -> String {
     );
 }
 
+/// Get the group and the decimal separator of a locale, e.g. de-DE.
+/// Returns None if the locale is invalid.
+pub fn locale_separators(locale: &str) -> Option<(char, char)> {
+    // Construct through reflection since the constructor throws for invalid locales
+    let constructor =
+        js_sys::Intl::NumberFormat::new(&js_sys::Array::new(), &js_sys::Object::new())
+            .constructor();
+    let args = js_sys::Array::of1(&locale.into());
+    let format: js_sys::Intl::NumberFormat = js_sys::Reflect::construct(&constructor, &args)
+        .ok()?
+        .unchecked_into();
+    let (mut group, mut decimal) = (None, None);
+    for part in format.format_to_parts(1234567.5).iter() {
+        let get = |key: &str| {
+            js_sys::Reflect::get(&part, &key.into())
+                .ok()
+                .and_then(|v| v.as_string())
+        };
+        match get("type").as_deref() {
+            Some("group") => group = get("value").and_then(|v| v.chars().next()),
+            Some("decimal") => decimal = get("value").and_then(|v| v.chars().next()),
+            _ => {}
+        }
+    }
+    Some((group?, decimal?))
+}
+
+/// Get the IANA name of the local time zone, e.g. Europe/Berlin
+pub fn local_time_zone() -> String {
+    let format = js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new());
+    js_sys::Reflect::get(&format.resolved_options(), &"timeZone".into())
+        .ok()
+        .and_then(|v| v.as_string())
+        .unwrap_or_else(|| "UTC".to_string())
+}
+
+thread_local! {
+    /// The date formats of time zones, constructing them is expensive
+    static ZONE_FORMATS: RefCell<HashMap<String, Option<js_sys::Intl::DateTimeFormat>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Construct a date format that shows the wall-clock time of a time zone
+fn zone_format(name: &str) -> Option<js_sys::Intl::DateTimeFormat> {
+    let options = js_sys::Object::new();
+    let set = |key: &str, value: &str| {
+        js_sys::Reflect::set(&options, &key.into(), &value.into()).unwrap();
+    };
+    set("timeZone", name);
+    set("hourCycle", "h23");
+    for key in ["year", "month", "day", "hour", "minute", "second"] {
+        set(key, "numeric");
+    }
+    // Construct through reflection since the constructor throws for invalid time zones
+    let constructor =
+        js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &js_sys::Object::new())
+            .constructor();
+    let args = js_sys::Array::of2(&"en-US".into(), &options);
+    js_sys::Reflect::construct(&constructor, &args)
+        .ok()
+        .map(|f| f.unchecked_into())
+}
+
+/// Get the offset of a time zone in seconds east of UTC at a UTC timestamp in milliseconds.
+/// Returns None if the time zone is unknown.
+pub fn zone_offset(name: &str, ms: i64) -> Option<i32> {
+    let format = ZONE_FORMATS.with(|formats| {
+        formats
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_insert_with(|| zone_format(name))
+            .clone()
+    })?;
+    let mut fields = HashMap::new();
+    for part in format
+        .format_to_parts(&js_sys::Date::new(&(ms as f64).into()))
+        .iter()
+    {
+        let get = |key: &str| {
+            js_sys::Reflect::get(&part, &key.into())
+                .ok()
+                .and_then(|v| v.as_string())
+        };
+        if let (Some(key), Some(value)) = (get("type"), get("value")) {
+            fields.insert(key, value.parse::<u32>().unwrap_or(0));
+        }
+    }
+    let field = |key: &str| fields.get(key).copied();
+    let local = NaiveDate::from_ymd_opt(field("year")? as i32, field("month")?, field("day")?)?
+        .and_hms_opt(field("hour")?, field("minute")?, field("second")?)?;
+    Some((local.timestamp() - ms.div_euclid(1000)) as i32)
+}
+
 pub fn pretty_bytes(num: f64) -> String {
     let negative = if num.is_sign_positive() { "" } else { "-" };
     let num = num.abs();
@@ -40,6 +40,16 @@ pub const CR: char = '\r';
 pub const CRLF: &str = "\r\n";
 pub const PARAGRAPH_SEPERATOR: char = '\u{2029}';
 pub const NEXT_LINE: char = '\u{0085}';
+pub const BRACKETED_PASTE_ON: &str = "\x1b[?2004h";
+pub const PASTE_BEGIN: &str = "\x1b[200~";
+pub const PASTE_END: &str = "\x1b[201~";
+pub const ALT_SCREEN_ON: &str = "\x1b[?1049h";
+pub const ALT_SCREEN_OFF: &str = "\x1b[?1049l";
+pub const CURSOR_HIDE: &str = "\x1b[?25l";
+pub const CURSOR_SHOW: &str = "\x1b[?25h";
+pub const MOUSE_TRACKING_ON: &str = "\x1b[?1000h\x1b[?1006h";
+pub const MOUSE_TRACKING_OFF: &str = "\x1b[?1006l\x1b[?1000l";
+pub const MOUSE_REPORT_BEGIN: &str = "\x1b[<";
 
 pub fn cursor_right<Buffer>(out: &mut Buffer, n: usize)
 where
@@ -59,6 +69,12 @@ where
 {
     write!(out, "\x1b[{}A", n).unwrap();
 }
+pub fn cursor_position<Buffer>(out: &mut Buffer, row: usize, col: usize)
+where
+    Buffer: std::fmt::Write,
+{
+    write!(out, "\x1b[{};{}H", row + 1, col + 1).unwrap();
+}
 pub fn cursor_down<Buffer>(out: &mut Buffer, n: usize)
 where
     Buffer: std::fmt::Write,
@@ -951,4 +951,7 @@ extern "C" {
     #[wasm_bindgen(method, getter, js_name = "activeVersion")]
     pub fn get_active_version(this: &UnicodeHandling) -> String;
 
+    #[wasm_bindgen(method, setter, js_name = "activeVersion")]
+    pub fn set_active_version(this: &UnicodeHandling, version: &str);
+
 }
@@ -8,9 +8,68 @@ interface ScriptTokens {
     types: Uint8Array;
 }
 
+/// A failed query with the fields of the error details
+export class QueryError extends Error {
+    /// The gRPC status code, e.g. Unavailable if the service cannot be reached
+    code?: string;
+    /// The SQLSTATE code
+    sqlstate?: string;
+    /// Additional details
+    detail?: string;
+    /// A hint how to fix the error
+    hint?: string;
+    /// The 1-based character position of the error in the query
+    position?: number;
+}
+
+/// Read the error details from the gRPC status details or the trailers
+function readQueryError(e: unknown): unknown {
+    if (e instanceof TypeError) {
+        // fetch rejects with a TypeError if the service cannot be reached
+        const error = new QueryError(e.message);
+        error.code = bufconnect.Code[bufconnect.Code.Unavailable];
+        return error;
+    }
+    if (!(e instanceof bufconnect.ConnectError)) {
+        return e;
+    }
+    const error = new QueryError(e.rawMessage);
+    error.code = bufconnect.Code[e.code];
+    for (const detail of e.details) {
+        const info = new proto.service_pb.ErrorInfo();
+        if (detail.unpackTo(info)) {
+            error.message = info.message || e.rawMessage;
+            error.sqlstate = info.sqlstate || undefined;
+            error.detail = info.detail || undefined;
+            error.hint = info.hint || undefined;
+            error.position = info.position || undefined;
+            return error;
+        }
+    }
+    const trailer = (key: string) => e.metadata.get(`loper-error-${key}`) ?? undefined;
+    error.sqlstate = trailer("sqlstate");
+    error.detail = trailer("detail");
+    error.hint = trailer("hint");
+    const position = trailer("position");
+    error.position = position ? Number.parseInt(position) : undefined;
+    return error;
+}
+
+/// The arrow chunks of a query result with their timings
+export interface QueryResult {
+    /// The arrow ipc chunks
+    buffers: Uint8Array[];
+    /// The milliseconds until the first chunk arrived
+    firstChunkTime: number;
+    /// The milliseconds until the last chunk arrived
+    lastChunkTime: number;
+}
+
 class LoperServiceConnection {
     /// The service client
     service: LoperServiceClient;
+    /// The session parameters, e.g. the time zone
+    params: Record<string, string> = {};
 
     constructor(service: LoperServiceClient) {
         this.service = service;
@@ -20,36 +79,83 @@ class LoperServiceConnection {
     public async disconnect(): Promise<number> {
         return 42;
     }
+    /// Send a trivial query to check that the service is reachable
+    public async ping(): Promise<void> {
+        await this.runQuery('SELECT 1');
+    }
+    /// Set a session parameter that is sent with every query
+    public setParam(key: string, value: string): void {
+        this.params[key] = value;
+    }
     /// Run a query
     public async runQuery(text: string): Promise<Uint8Array[]> {
+        return (await this.runQueryWithStats(text)).buffers;
+    }
+    /// Run a query and measure when the chunks arrive
+    public async runQueryWithStats(text: string): Promise<QueryResult> {
         const request = new proto.service_pb.QueryParam({
-            query: text
+            query: text,
+            params: this.params,
         });
+        const start = performance.now();
         let buffers = [];
-        for await (const response of this.service.client.executeQuery(request)) {
-            switch (response.result.case) {
-                case "arrowIpcDataChunk": {
-                    buffers.push(response.result.value.data);
+        let firstChunkTime: number | null = null;
+        let lastChunkTime = 0;
+        try {
+            for await (const response of this.service.client.executeQuery(request)) {
+                lastChunkTime = performance.now() - start;
+                if (firstChunkTime === null) {
+                    firstChunkTime = lastChunkTime;
+                }
+                switch (response.result.case) {
+                    case "arrowIpcDataChunk": {
+                        buffers.push(response.result.value.data);
+                    }
                 }
             }
+        } catch (e) {
+            throw readQueryError(e);
         }
-        return buffers;
+        return {
+            buffers,
+            firstChunkTime: firstChunkTime ?? lastChunkTime,
+            lastChunkTime,
+        };
     }
 }
 
 export class LoperServiceClient {
     client: bufconnect.PromiseClient<typeof proto.service_grpc.HyperDatabaseService>;
     public url: string;
+    public metadata: Record<string, string>;
 
-    constructor(url: string, credentials?: RequestCredentials) {
+    constructor(url: string, credentials?: RequestCredentials, metadata?: Record<string, string>) {
         this.url = url;
+        this.metadata = metadata ?? {};
+        // Attach the metadata, e.g. auth tokens, as headers to every request
+        const headers = this.metadata;
+        const withMetadata: bufconnect.Interceptor = next => async req => {
+            for (const [key, value] of Object.entries(headers)) {
+                req.header.set(key, value);
+            }
+            return await next(req);
+        };
         const transport = bufconnect.createGrpcWebTransport({
             baseUrl: url,
-            credentials
+            credentials,
+            interceptors: [withMetadata],
         });
         this.client = bufconnect.createPromiseClient(proto.service_grpc.HyperDatabaseService, transport);
     }
 
+    /// Does the client send the metadata?
+    public hasMetadata(metadata: Record<string, string>): boolean {
+        const keys = Object.keys(metadata);
+        return (
+            keys.length == Object.keys(this.metadata).length && keys.every(key => this.metadata[key] === metadata[key])
+        );
+    }
+
     /// Get version
     public async getVersion(): Promise<string> { return "someversion"; }
     /// Tokenize a text
@@ -1,5 +1,6 @@
 import * as shell from '../crate/pkg';
-import { HistoryStore } from './utils/history_store';
+import { HistoryEntry, HistoryStore } from './utils/history_store';
+import { ConnectionProfile, ProfileStore } from './utils/profile_store';
 import { LoperServiceClient } from './database';
 
 export const isNode = () => (typeof navigator === 'undefined' ? true : false);
@@ -20,11 +21,13 @@ const hasWebGL = (): boolean => {
 
 class ShellRuntime {
     history: HistoryStore;
+    profiles: ProfileStore;
     service: LoperServiceClient | null;
     resizeHandler: (_event: UIEvent) => void;
 
     constructor(protected container: HTMLDivElement) {
         this.history = new HistoryStore();
+        this.profiles = new ProfileStore();
         this.service = null;
         this.resizeHandler = (_event: UIEvent) => {
             const rect = container.getBoundingClientRect();
@@ -32,14 +35,21 @@ class ShellRuntime {
         };
     }
 
-    public async configureClient(this: ShellRuntime, url: string): Promise<LoperServiceClient> {
+    public async configureClient(
+        this: ShellRuntime,
+        url: string,
+        metadata?: Record<string, string>,
+    ): Promise<LoperServiceClient> {
         if (this.service) {
-            if (this.service?.url == url) {
+            if (this.service?.url == url && this.service.hasMetadata(metadata ?? {})) {
                 return this.service;
             }
         }
-        this.service = new LoperServiceClient(url);
-        return this.service;
+        // The client is kept once the shell connected with it
+        return new LoperServiceClient(url, undefined, metadata);
+    }
+    public useClient(this: ShellRuntime, client: LoperServiceClient) {
+        this.service = client;
     }
     public async readClipboardText(this: ShellRuntime): Promise<string> {
         return await navigator.clipboard.readText();
@@ -47,8 +57,23 @@ class ShellRuntime {
     public async writeClipboardText(this: ShellRuntime, value: string) {
         return await navigator.clipboard.writeText(value);
     }
-    public async pushInputToHistory(this: ShellRuntime, value: string) {
-        this.history.push(value);
+    public async pushHistoryEntry(this: ShellRuntime, entry: HistoryEntry) {
+        this.history.push(entry);
+    }
+    public async saveProfiles(this: ShellRuntime, profiles: ConnectionProfile[]) {
+        this.profiles.save(profiles);
+    }
+    public async downloadFile(this: ShellRuntime, name: string, content: string, mimeType: string) {
+        const blob = new Blob([content], { type: mimeType });
+        const url = URL.createObjectURL(blob);
+        const link = document.createElement('a');
+        link.href = url;
+        link.download = name;
+        // Some browsers ignore clicks on detached links or cancel the download if the url is revoked right away
+        document.body.appendChild(link);
+        link.click();
+        document.body.removeChild(link);
+        setTimeout(() => URL.revokeObjectURL(url), 1000);
     }
 }
 
@@ -96,6 +121,9 @@ export async function embed(props: ShellProps) {
         const [hist, histCursor] = await runtime.history.load();
         shell.loadHistory(hist, histCursor);
     });
+    await step('Loading Connection Profiles', async () => {
+        shell.loadProfiles(runtime.profiles.load());
+    });
     shell.writeln(`${TERM_BOLD}[ RUN ]${TERM_NORMAL} Setup Loper Service Client`);
     await shell.initialSetup();
 }
@@ -4,10 +4,25 @@ const TABLE_LOG_INFO = 'LOG_INFO';
 const TABLE_LOG_ENTRIES = 'LOG_ENTRIES';
 const HISTORY_SIZE_SHIFT = 10; // 1 << 10 = 1024 elements
 
+export interface HistoryEntry {
+    input: string;
+    timestamp: number;
+    elapsed?: number | null;
+    rows?: number | null;
+    success?: boolean | null;
+    error?: string | null;
+    url?: string | null;
+}
+
 interface LogEntry {
     key: number;
     when: Date;
     input: string;
+    elapsed?: number | null;
+    rows?: number | null;
+    success?: boolean | null;
+    error?: string | null;
+    url?: string | null;
 }
 
 interface LogInfo {
@@ -30,7 +45,7 @@ export class HistoryStore {
     }
 
     /// Load entire history
-    public async load(): Promise<[string[], number]> {
+    public async load(): Promise<[HistoryEntry[], number]> {
         if (this._entryCount == 0) return [[], 0];
 
         // Update in indexeddb
@@ -40,11 +55,20 @@ export class HistoryStore {
 
         // Collect all history entries
         return await new Promise((resolve, reject) => {
-            const results: string[] = [];
+            const results: HistoryEntry[] = [];
             cursor.onsuccess = event => {
                 const req = event.target as IDBRequest<IDBCursorWithValue | null>;
                 if (req.result != null) {
-                    results.push((req.result.value as LogEntry).input);
+                    const entry = req.result.value as LogEntry;
+                    results.push({
+                        input: entry.input,
+                        timestamp: entry.when.getTime(),
+                        elapsed: entry.elapsed,
+                        rows: entry.rows,
+                        success: entry.success,
+                        error: entry.error,
+                        url: entry.url,
+                    });
                     req.result.continue();
                 } else {
                     resolve([results, this._nextEntryKey]);
@@ -118,7 +142,7 @@ export class HistoryStore {
     }
 
     /// Push a new entry
-    public async push(input: string): Promise<void> {
+    public async push(entry: HistoryEntry): Promise<void> {
         // Get next key
         const entryKey = this._nextEntryKey++ & ((1 << HISTORY_SIZE_SHIFT) - 1);
         this._entryCount = Math.min(this._entryCount + 1, 1 << HISTORY_SIZE_SHIFT);
@@ -133,8 +157,13 @@ export class HistoryStore {
             new Promise((resolve, reject) => {
                 const r = logEntries.put({
                     key: entryKey,
-                    when: new Date(),
-                    input: input,
+                    when: new Date(entry.timestamp),
+                    input: entry.input,
+                    elapsed: entry.elapsed,
+                    rows: entry.rows,
+                    success: entry.success,
+                    error: entry.error,
+                    url: entry.url,
                 });
                 r.onsuccess = resolve;
                 r.onerror = reject;
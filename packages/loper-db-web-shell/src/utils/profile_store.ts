@@ -0,0 +1,35 @@
+/// The profiles are stored unencrypted, including the metadata with credentials such as auth tokens
+const STORAGE_KEY = 'HYPER_WEB_SHELL_PROFILES';
+
+export interface ConnectionProfile {
+    name: string;
+    url: string;
+    metadata: Record<string, string>;
+}
+
+export class ProfileStore {
+    protected _storage: Storage | null;
+
+    public constructor() {
+        this._storage = typeof window !== 'undefined' ? window.localStorage : null;
+    }
+
+    /// Load the connection profiles
+    public load(): ConnectionProfile[] {
+        const json = this._storage?.getItem(STORAGE_KEY);
+        if (!json) {
+            return [];
+        }
+        try {
+            return JSON.parse(json) as ConnectionProfile[];
+        } catch (e) {
+            console.warn(`failed to parse connection profiles: ${e}`);
+            return [];
+        }
+    }
+
+    /// Save the connection profiles
+    public save(profiles: ConnectionProfile[]) {
+        this._storage?.setItem(STORAGE_KEY, JSON.stringify(profiles));
+    }
+}